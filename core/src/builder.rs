@@ -0,0 +1,221 @@
+//! Generating code from a build script.
+
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Input, Options, Result};
+
+/// Generates code from CSV files into `OUT_DIR`, for use in `build.rs`.
+///
+/// ```rust,ignore
+/// // build.rs
+/// fn main()
+/// {
+///     csv2struct::Builder::new()
+///     .input("data/trades.csv")
+///     .name("Trade")
+///     .generate("trade.rs")
+///     .unwrap();
+/// }
+///
+/// // src/lib.rs
+/// include!(concat!(env!("OUT_DIR"), "/trade.rs"));
+/// ```
+///
+/// Cargo is told to run the build script again whenever one of the files
+/// changes, and any warnings are passed on to it. The file is only written if
+/// the code has changed.
+#[derive(Debug, Clone, Default)]
+pub struct Builder
+{
+    // the files to read
+    inputs: Vec<Input>,
+
+    options: Options,
+
+    // the name of the struct, when there's one for all of the files
+    name: Option<String>,
+
+    // whether the files may have different columns
+    merge: bool,
+
+    // whether to generate a struct for each file, rather than one for all
+    per_file: bool,
+}
+
+impl Builder
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Adds a file to read, relative to the directory holding `Cargo.toml`.
+    pub fn input<P: AsRef<Path>>(mut self, path: P) -> Self
+    {
+        self.inputs.push(Input::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Uses `options` instead of the defaults.
+    pub fn options(mut self, options: Options) -> Self
+    {
+        self.options = options;
+        self
+    }
+
+    /// Calls the struct `name`, rather than `Record`.
+    pub fn name(mut self, name: &str) -> Self
+    {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Allows the files to have different columns, as with `--merge`.
+    pub fn merge(mut self, merge: bool) -> Self
+    {
+        self.merge = merge;
+        self
+    }
+
+    /// Generates a struct for each file, named after it, as with
+    /// `--per-file`.
+    pub fn per_file(mut self, per_file: bool) -> Self
+    {
+        self.per_file = per_file;
+        self
+    }
+
+    /// Writes the code to `file_name` in `OUT_DIR`, returning its path.
+    pub fn generate(&self, file_name: &str) -> Result<PathBuf>
+    {
+        let out_dir =
+            env::var_os("OUT_DIR")
+            .ok_or_else(|| Error::Msg("OUT_DIR isn't set; Builder is for build scripts".to_string()))?;
+
+        self.generate_in(Path::new(&out_dir), file_name, &mut io::stdout())
+    }
+
+    /// Writes the code to `file_name` in `out_dir`, and the instructions for
+    /// Cargo to `cargo`.
+    fn generate_in(&self, out_dir: &Path, file_name: &str, cargo: &mut dyn Write) -> Result<PathBuf>
+    {
+        for input in self.inputs.iter()
+        {
+            writeln!(cargo, "cargo:rerun-if-changed={}", input)?;
+        }
+
+        let schemas = crate::infer_inputs(&self.inputs, self.name.as_deref(), self.merge, self.per_file, &self.options)?;
+
+        for warning in schemas.iter().flat_map(|s| s.warnings.iter())
+        {
+            writeln!(cargo, "cargo:warning={}", warning)?;
+        }
+
+        let path = out_dir.join(file_name);
+
+        // leave the file alone if it hasn't changed, so that nothing is
+        // rebuilt needlessly
+        crate::write_if_changed(&path, &crate::render_all(&schemas, &self.options)?)?;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    /// An empty directory for a test to use.
+    fn temp_dir(name: &str) -> PathBuf
+    {
+        let dir = env::temp_dir().join(format!("csv2struct-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn modified(path: &Path) -> SystemTime
+    {
+        fs::metadata(path).unwrap().modified().unwrap()
+    }
+
+    #[test]
+    fn generates_into_out_dir()
+    {
+        let dir = temp_dir("builder-out-dir");
+        let input = dir.join("trades.csv");
+        fs::write(&input, "id,price\n1,2.5\n").unwrap();
+
+        env::set_var("OUT_DIR", &dir);
+        let path = Builder::new().input(&input).name("Trade").generate("trade.rs").unwrap();
+
+        assert_eq!(path, dir.join("trade.rs"));
+        assert!(fs::read_to_string(&path).unwrap().contains("pub struct Trade"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tells_cargo_about_inputs_and_warnings()
+    {
+        let dir = temp_dir("builder-cargo");
+        let one = dir.join("one.csv");
+        let two = dir.join("two.csv");
+        fs::write(&one, "id,price\n1,2.5\n").unwrap();
+        fs::write(&two, "id\n2\n").unwrap();
+
+        let mut cargo = Vec::new();
+        Builder::new().input(&one).input(&two).merge(true).generate_in(&dir, "out.rs", &mut cargo).unwrap();
+
+        let cargo = String::from_utf8(cargo).unwrap();
+        let lines: Vec<&str> = cargo.lines().collect();
+
+        assert_eq!(lines[0], format!("cargo:rerun-if-changed={}", one.display()));
+        assert_eq!(lines[1], format!("cargo:rerun-if-changed={}", two.display()));
+        assert!(lines[2].starts_with("cargo:warning=column \"price\" is missing from"));
+        assert_eq!(lines.len(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_writes_when_the_code_changes()
+    {
+        let dir = temp_dir("builder-unchanged");
+        let input = dir.join("trades.csv");
+        fs::write(&input, "id,price\n1,2.5\n").unwrap();
+
+        let builder = Builder::new().input(&input);
+        let path = builder.generate_in(&dir, "out.rs", &mut io::sink()).unwrap();
+
+        // wind the clock back, so a rewrite would show
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&path).unwrap().set_modified(then).unwrap();
+
+        builder.generate_in(&dir, "out.rs", &mut io::sink()).unwrap();
+        assert_eq!(modified(&path), then);
+
+        fs::write(&input, "id,price\n1,2.5\n2,x\n").unwrap();
+        builder.generate_in(&dir, "out.rs", &mut io::sink()).unwrap();
+        assert_ne!(modified(&path), then);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checks_the_options_fit_together()
+    {
+        let dir = temp_dir("builder-options");
+
+        assert!(Builder::new().merge(true).per_file(true).generate_in(&dir, "out.rs", &mut io::sink()).is_err());
+        assert!(Builder::new().name("Trade").per_file(true).generate_in(&dir, "out.rs", &mut io::sink()).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! How a CSV file is laid out: its delimiter, quoting, comments and so on.
+
+use std::io::{self, Chain, Cursor, Read};
+
+use csv::{ReaderBuilder, Terminator, Trim};
+
+use crate::{Error, Result};
+
+/// Delimiters we look for when sniffing, most likely first.
+const DELIMITERS: &[u8] = b",\t;|";
+
+/// How much of the input we look at when sniffing.
+const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// How many lines of the sample we look at when sniffing.
+const SAMPLE_LINES: usize = 20;
+
+/// The settings for `csv::ReaderBuilder`.
+#[derive(Debug, Clone)]
+pub struct Dialect
+{
+    /// The delimiter, or `None` to sniff it.
+    pub delimiter: Option<u8>,
+
+    /// The character that quotes fields.
+    pub quote: u8,
+
+    /// The character that escapes quotes inside quoted fields, if any.
+    pub escape: Option<u8>,
+
+    /// Whether two quotes in a row inside a quoted field stand for one.
+    pub double_quote: bool,
+
+    /// The character that starts a comment line, if any.
+    pub comment: Option<u8>,
+
+    /// What ends a record.
+    pub terminator: Terminator,
+
+    /// Whether to trim whitespace from headers, fields, or both.
+    pub trim: Trim,
+
+    /// Whether the first row is a header.
+    pub header: bool,
+}
+
+impl Default for Dialect
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            delimiter: None,
+            quote: b'"',
+            escape: None,
+            double_quote: true,
+            comment: None,
+            terminator: Terminator::CRLF,
+            trim: Trim::None,
+            header: true,
+        }
+    }
+}
+
+impl Dialect
+{
+    /// A reader for `input`, working out the delimiter from the first few
+    /// lines if it wasn't given.
+    pub fn reader<R: Read>(&self, mut input: R) -> io::Result<csv::Reader<Chain<Cursor<Vec<u8>>, R>>>
+    {
+        let mut sample = Vec::new();
+
+        let delimiter = match self.delimiter
+        {
+            Some(delimiter) => delimiter,
+
+            None =>
+            {
+                (&mut input).take(SAMPLE_SIZE).read_to_end(&mut sample)?;
+                self.sniff(&sample, sample.len() as u64 == SAMPLE_SIZE)
+            }
+        };
+
+        // put the sample, if any, back in front of the rest
+        let input = Cursor::new(sample).chain(input);
+
+        Ok(
+            ReaderBuilder::new()
+            .delimiter(delimiter)
+            .quote(self.quote)
+            .escape(self.escape)
+            .double_quote(self.double_quote)
+            .comment(self.comment)
+            .terminator(self.terminator)
+            .trim(self.trim)
+            .has_headers(self.header)
+            .from_reader(input)
+        )
+    }
+
+    /// Picks the delimiter that splits the lines of `sample` into the same
+    /// number of fields most consistently, preferring more fields, and the
+    /// earlier delimiter in `DELIMITERS` on a tie. Falls back to a comma.
+    ///
+    /// If `truncated` then the last line is probably cut short, so it's left
+    /// out.
+    fn sniff(&self, sample: &[u8], truncated: bool) -> u8
+    {
+        let mut lines = self.lines(sample);
+
+        if truncated && lines.len() > 1
+        {
+            lines.pop();
+        }
+
+        lines.truncate(SAMPLE_LINES);
+
+        let mut best = (b',', 0, 0);
+
+        for &delimiter in DELIMITERS.iter()
+        {
+            let counts: Vec<usize> =
+                lines.iter()
+                .map(|line| self.count(line, delimiter))
+                .collect();
+
+            let first = match counts.first()
+            {
+                Some(&first) if first > 0 => first,
+                _ => continue,
+            };
+
+            let consistent = counts.iter().filter(|&&c| c == first).count();
+
+            if (consistent, first) > (best.1, best.2)
+            {
+                best = (delimiter, consistent, first);
+            }
+        }
+
+        best.0
+    }
+
+    /// Splits `sample` into lines, ignoring line breaks inside quotes, and
+    /// leaving out blank lines and comments.
+    fn lines<'a>(&self, sample: &'a [u8]) -> Vec<&'a [u8]>
+    {
+        let is_break = |b: u8| match self.terminator
+        {
+            Terminator::Any(t) => b == t,
+            _ => b == b'\n' || b == b'\r',
+        };
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut quoted = false;
+
+        for (i, &b) in sample.iter().enumerate()
+        {
+            if b == self.quote
+            {
+                quoted = !quoted;
+            }
+            else if !quoted && is_break(b)
+            {
+                lines.push(&sample[start..i]);
+                start = i + 1;
+            }
+        }
+
+        lines.push(&sample[start..]);
+
+        lines.retain(|line| !line.is_empty() && self.comment != Some(line[0]));
+        lines
+    }
+
+    /// How many times `delimiter` appears in `line`, outside of quotes.
+    fn count(&self, line: &[u8], delimiter: u8) -> usize
+    {
+        let mut quoted = false;
+        let mut count = 0;
+
+        for &b in line.iter()
+        {
+            if b == self.quote
+            {
+                quoted = !quoted;
+            }
+            else if !quoted && b == delimiter
+            {
+                count += 1;
+            }
+        }
+
+        count
+    }
+}
+
+/// Parses a command line value that should be a single ASCII character; `\t`
+/// and `tab` are also accepted for a tab.
+pub fn byte(value: &str) -> Result<u8>
+{
+    match value
+    {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err(Error::Msg(format!("expected a single ASCII character, not {:?}", value))),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn sniff(sample: &str) -> u8
+    {
+        Dialect::default().sniff(sample.as_bytes(), false)
+    }
+
+    #[test]
+    fn sniffs_tabs()
+    {
+        assert_eq!(sniff("a\tb\tc\n1\t2\t3\n4\t5\t6\n"), b'\t');
+    }
+
+    #[test]
+    fn sniffs_semicolons()
+    {
+        assert_eq!(sniff("a;b;c\n1,5;2,5;3\n4;5;6\n"), b';');
+    }
+
+    #[test]
+    fn ignores_commas_in_quotes()
+    {
+        assert_eq!(sniff("name|note\nx|\"a, b, c\"\ny|\"d, e\"\n"), b'|');
+    }
+
+    #[test]
+    fn falls_back_to_commas()
+    {
+        assert_eq!(sniff("just one column\nand another line\n"), b',');
+    }
+}
@@ -0,0 +1,271 @@
+//! Turning arbitrary CSV text into valid, unique Rust identifiers.
+
+use std::collections::HashSet;
+
+use inflector::Inflector;
+
+/// Keywords, including reserved ones, in any edition.
+const KEYWORDS: &[&str] =
+&[
+    "abstract", "as", "async", "await", "become", "box", "break", "const",
+    "continue", "crate", "do", "dyn", "else", "enum", "extern", "false",
+    "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "self", "Self", "static", "struct", "super", "trait", "true", "try",
+    "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while",
+    "yield",
+];
+
+/// Keywords that can't be used as raw identifiers either.
+const NOT_RAW: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Type names that the generated code refers to, so an enum can't use them.
+pub const RESERVED_TYPES: &[&str] =
+&[
+    "Option", "Some", "None", "Result", "Ok", "Err", "String", "Vec",
+    "Box",
+];
+
+/// Converts a column header into a snake_case field name.
+///
+/// Symbols that carry meaning are spelled out, so `%change` becomes
+/// `percent_change`; anything else that can't appear in an identifier is
+/// dropped. Names that would start with a digit get a `field_` prefix, and
+/// keywords are escaped with `r#` (or a `_` suffix where that isn't allowed).
+pub fn field_name(header: &str) -> String
+{
+    let mut name = clean(&spell_symbols(header).to_snake_case());
+
+    if name.is_empty()
+    {
+        name = String::from("field");
+    }
+    else if name.starts_with(|c: char| c.is_ascii_digit())
+    {
+        name = format!("field_{}", name);
+    }
+
+    escape(name)
+}
+
+/// Converts a factor level into a PascalCase enum variant.
+///
+/// This works like `field_name`, so `in-progress` becomes `InProgress`, but
+/// levels starting with a digit get a `Level` prefix and levels with nothing
+/// usable in them at all are just `Level`.
+pub fn variant_name(level: &str) -> String
+{
+    let name = clean(&spell_symbols(level).to_snake_case());
+
+    if name.is_empty()
+    {
+        String::from("Level")
+    }
+    else if name.starts_with(|c: char| c.is_ascii_digit())
+    {
+        escape(format!("level_{}", name).to_pascal_case())
+    }
+    else
+    {
+        escape(name.to_pascal_case())
+    }
+}
+
+/// Converts a file name (minus its extension) into the name of the struct for
+/// its records, so `orders` becomes `Order`.
+pub fn struct_name(stem: &str) -> String
+{
+    let name = clean(&spell_symbols(stem).to_snake_case()).to_singular();
+
+    if name.is_empty()
+    {
+        String::from("Record")
+    }
+    else if name.starts_with(|c: char| c.is_ascii_digit())
+    {
+        escape(format!("record_{}", name).to_pascal_case())
+    }
+    else
+    {
+        escape(name.to_pascal_case())
+    }
+}
+
+/// Converts a field name into a PascalCase type name, escaped like the field
+/// name was; so the type for a field `self_` is `Self_`.
+pub fn type_name(field: &str) -> String
+{
+    escape(unraw(field).to_pascal_case())
+}
+
+/// The name as it appears in source, minus any `r#`.
+pub fn unraw(name: &str) -> &str
+{
+    name.trim_start_matches("r#")
+}
+
+/// Spells out symbols that mean something, rather than dropping them.
+fn spell_symbols(text: &str) -> String
+{
+    text.chars()
+    .map(|c| match c
+    {
+        '%' => " percent ".to_string(),
+        '#' => " number ".to_string(),
+        '&' => " and ".to_string(),
+        '+' => " plus ".to_string(),
+        '@' => " at ".to_string(),
+        c => c.to_string(),
+    })
+    .collect()
+}
+
+/// Replaces anything but ASCII letters, digits and underscores with an
+/// underscore, then squashes runs of underscores and trims them off the ends.
+fn clean(name: &str) -> String
+{
+    let mut out = String::new();
+
+    for c in name.chars()
+    {
+        let c = if c.is_ascii_alphanumeric() { c } else { '_' };
+
+        if !(c == '_' && (out.is_empty() || out.ends_with('_')))
+        {
+            out.push(c);
+        }
+    }
+
+    out.trim_end_matches('_').to_string()
+}
+
+/// Makes keywords usable as identifiers.
+fn escape(name: String) -> String
+{
+    if NOT_RAW.contains(&name.as_str())
+    {
+        format!("{}_", name)
+    }
+    else if KEYWORDS.contains(&name.as_str())
+    {
+        format!("r#{}", name)
+    }
+    else
+    {
+        name
+    }
+}
+
+/// Hands out names that haven't been used yet, by adding a number to the end
+/// of any that have.
+#[derive(Debug, Default)]
+pub struct Namer
+{
+    used: HashSet<String>,
+}
+
+impl Namer
+{
+    /// A namer that won't hand out any of `names`.
+    pub fn reserving(names: &[&str]) -> Self
+    {
+        Self
+        {
+            used: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    /// Whether `name` has been handed out, or reserved.
+    pub fn contains(&self, name: &str) -> bool
+    {
+        self.used.contains(unraw(name))
+    }
+
+    /// Returns `name` if it's free, or else the first free one of `name2`,
+    /// `name3`, ... (or `name_2`, ... when `separator` is `"_"`).
+    pub fn unique(&mut self, name: String, separator: &str) -> String
+    {
+        let mut candidate = name.clone();
+        let mut n = 2;
+
+        while self.used.contains(unraw(&candidate))
+        {
+            candidate = format!("{}{}{}", unraw(&name), separator, n);
+            n += 1;
+        }
+
+        self.used.insert(unraw(&candidate).to_string());
+        candidate
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn field_names_escape_keywords()
+    {
+        assert_eq!(field_name("type"), "r#type");
+        assert_eq!(field_name("Match"), "r#match");
+        assert_eq!(field_name("self"), "self_");
+        assert_eq!(field_name("crate"), "crate_");
+    }
+
+    #[test]
+    fn field_names_starting_with_digits()
+    {
+        assert_eq!(field_name("2nd place"), "field_2nd_place");
+        assert_eq!(field_name("%change"), "percent_change");
+        assert_eq!(field_name("???"), "field");
+    }
+
+    #[test]
+    fn type_names_are_escaped_like_field_names()
+    {
+        assert_eq!(type_name("r#type"), "Type");
+        assert_eq!(type_name("self_"), "Self_");
+        assert_eq!(type_name("order_status"), "OrderStatus");
+    }
+
+    #[test]
+    fn colliding_field_names_are_numbered()
+    {
+        let mut namer = Namer::default();
+
+        assert_eq!(namer.unique(field_name("Price"), "_"), "price");
+        assert_eq!(namer.unique(field_name("price"), "_"), "price_2");
+        assert_eq!(namer.unique(field_name("PRICE"), "_"), "price_3");
+        assert_eq!(namer.unique(field_name("type"), "_"), "r#type");
+        assert!(namer.contains("type"));
+    }
+
+    #[test]
+    fn variant_names()
+    {
+        assert_eq!(variant_name("in-progress"), "InProgress");
+        assert_eq!(variant_name("1"), "Level1");
+        assert_eq!(variant_name("Self"), "Self_");
+        assert_eq!(variant_name("--"), "Level");
+    }
+
+    #[test]
+    fn colliding_variant_names_are_numbered()
+    {
+        let mut namer = Namer::reserving(&["Option"]);
+
+        assert_eq!(namer.unique(variant_name("open"), ""), "Open");
+        assert_eq!(namer.unique(variant_name("OPEN"), ""), "Open2");
+        assert_eq!(namer.unique(variant_name("option"), ""), "Option2");
+    }
+
+    #[test]
+    fn struct_names_are_singular()
+    {
+        assert_eq!(struct_name("orders"), "Order");
+        assert_eq!(struct_name("order-lines"), "OrderLine");
+        assert_eq!(struct_name("2024_sales"), "Record2024Sale");
+        assert_eq!(struct_name("---"), "Record");
+    }
+}
@@ -0,0 +1,902 @@
+//! Reading CSV and keeping a running summary of each column, from which we
+//! decide what the columns are.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::number;
+use crate::schema::{Column, ColumnKind, Precision, Schema};
+use crate::temporal::Epoch;
+use crate::{Error, LevelOrder, Options, Result};
+
+/// Works out a schema from one or more inputs. Their columns are matched up
+/// by name, and columns that only some of them have become optional.
+#[derive(Debug)]
+pub struct Inference<'a>
+{
+    options: &'a Options,
+
+    // the summaries of the columns
+    index: Index,
+
+    // the name of each input read so far, and its headers
+    sources: Vec<(String, Vec<String>)>,
+
+    // anything the user should know about the inputs
+    warnings: Vec<String>,
+}
+
+impl<'a> Inference<'a>
+{
+    pub fn new(options: &'a Options) -> Self
+    {
+        Self
+        {
+            options,
+            index: Index::new(),
+            sources: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Reads every record from `reader` into the index, returning the
+    /// headers. `source` says where the records came from in errors and
+    /// warnings.
+    pub fn read<R: Read>(&mut self, reader: R, source: &str) -> Result<Vec<String>>
+    {
+        let opts = self.options;
+
+        // tell the user which file any error came from
+        let context = |e: csv::Error| Error::Msg(format!("{}: {}", source, e));
+
+        let mut rdr =
+            opts.dialect.reader(reader)
+            .map_err(|e| Error::Msg(format!("{}: {}", source, e)))?;
+
+        let first: Vec<String> =
+            rdr.headers().map_err(context)?.iter()
+            .map(|h| h.to_string())
+            .collect();
+
+        let headers = if opts.dialect.header
+        {
+            first.clone()
+        }
+        else if opts.names.len() > first.len()
+        {
+            return Err(Error::Msg(format!(
+                "{}: {} names were given, but there are only {} columns",
+                source, opts.names.len(), first.len()
+            )));
+        }
+        else
+        {
+            let mut names = opts.names.clone();
+            names.extend((names.len()..first.len()).map(|i| format!("column_{}", i + 1)));
+            names
+        };
+
+        // what the first row looks like, and whether each column has typed
+        // values and text below it, to check that the first row is (or isn't) a
+        // header
+        let mut first_kinds: Vec<FieldKind> = Vec::new();
+        let mut below = vec![(false, false); headers.len()];
+
+        if opts.dialect.header
+        {
+            // not `parse`, since a header like `y` is more likely a name than a
+            // boolean
+            first_kinds = first.iter().map(|h| FieldKind::parse_value(h, opts)).collect();
+        }
+
+        // which summary each column goes in, worked out once rather than for
+        // every value; not until there's a record, so that a file with only a
+        // header adds no columns
+        let mut positions = None;
+
+        // the record is reused, so that reading one doesn't allocate
+        let mut record = csv::StringRecord::new();
+        let mut row = 0;
+
+        while rdr.read_record(&mut record).map_err(context)?
+        {
+            self.index.rows += 1;
+
+            let positions = positions.get_or_insert_with(|| self.index.positions(&headers));
+
+            for (i, (&position, value)) in positions.iter().zip(record.iter()).enumerate()
+            {
+                let kind = FieldKind::parse(value, opts);
+
+                if !opts.dialect.header && row == 0
+                {
+                    first_kinds.push(kind.clone());
+                }
+                else
+                {
+                    match kind
+                    {
+                        FieldKind::Empty | FieldKind::Null => (),
+                        FieldKind::Factor => below[i].1 = true,
+                        _ => below[i].0 = true,
+                    }
+                }
+
+                self.index.inner[position].1.add(kind, value, opts);
+            }
+
+            row += 1;
+        }
+
+        let all_text =
+            !first_kinds.is_empty()
+            && first_kinds.iter().all(|k| matches!(k, FieldKind::Factor));
+
+        let all_numbers =
+            !first_kinds.is_empty()
+            && first_kinds.iter().all(|k| matches!(k, FieldKind::Integer(_) | FieldKind::Real(..) | FieldKind::Temporal { .. }));
+
+        let typed_column = below.iter().any(|&(typed, text)| typed && !text);
+
+        if opts.dialect.header && all_numbers
+        {
+            self.warnings.push(format!(
+                "the first row of {} looks like data rather than a header; \
+                 pass --no-header if it is",
+                source
+            ));
+        }
+        else if !opts.dialect.header && all_text && typed_column
+        {
+            self.warnings.push(format!(
+                "the first row of {} looks like a header rather than data; \
+                 leave out --no-header if it is",
+                source
+            ));
+        }
+
+        self.sources.push((source.to_string(), headers.clone()));
+
+        Ok(headers)
+    }
+
+    /// The schema for everything read, with a struct called `name`.
+    pub fn finish(mut self, name: &str) -> Schema
+    {
+        let headers: Vec<Vec<String>> = self.sources.iter().map(|(_, h)| h.clone()).collect();
+
+        for (column, lacking) in self.index.unify(&headers)
+        {
+            let lacking: Vec<&str> = lacking.iter().map(|&i| self.sources[i].0.as_str()).collect();
+
+            self.warnings.push(format!(
+                "column {:?} is missing from {}, so it is optional",
+                column, lacking.join(", ")
+            ));
+        }
+
+        self.index.to_schema(name, self.warnings, self.options)
+    }
+}
+
+/// Represents the different kinds of fields that a record can have
+#[derive(Debug, Clone)]
+enum FieldKind
+{
+    Boolean,
+    Integer(i128),
+    // the float type it needs, and how many decimal places it was written
+    // with; `None` if it was written in a way `Decimal` can't read, like `1e6`
+    Real(Precision, Option<usize>),
+    Factor,
+    Temporal
+    {
+        // indices of the formats in `Options::date_formats` that match; these
+        // are all of the same kind
+        formats: Vec<usize>,
+    },
+    Empty,
+
+    // one of the values that mean a value is missing, like `NA`
+    Null,
+
+    // a number with formatting that had to be stripped off to read it, like
+    // `$1,234.50`
+    Formatted(Box<FieldKind>),
+}
+
+impl FieldKind
+{
+    fn parse(f: &str, opts: &Options) -> Self
+    {
+        if f.is_empty()
+        {
+            FieldKind::Empty
+        }
+        else if opts.null_values.iter().any(|v| v == f)
+        {
+            FieldKind::Null
+        }
+        else if opts.is_boolean(f)
+        {
+            FieldKind::Boolean
+        }
+        else
+        {
+            Self::parse_value(f, opts)
+        }
+    }
+
+    /// Parses a non-empty value as a number, date or time, or factor level.
+    ///
+    /// This is also used to re-read boolean values in columns that turn out
+    /// not to be boolean; e.g. `1` in a column which also contains `2`.
+    fn parse_value(f: &str, opts: &Options) -> Self
+    {
+        if let Some(number) = number::strip(f, opts.number_format).filter(|n| n != f)
+        {
+            return FieldKind::Formatted(Box::new(Self::parse_value(&number, opts)));
+        }
+
+            f.parse::<i128>().map(FieldKind::Integer)
+        .or(f.parse::<f64>().map(|r| FieldKind::Real(Precision::of(r), number::scale(f))))
+        .unwrap_or_else(|_| Self::parse_temporal(f, opts))
+    }
+
+    /// Parses a value that isn't a number as a date or time if it matches any
+    /// of the formats, otherwise as a factor level.
+    fn parse_temporal(f: &str, opts: &Options) -> Self
+    {
+        let mut matching =
+            opts.date_formats.iter().enumerate()
+            .filter(|(_, format)| format.matches(f));
+
+        match matching.next()
+        {
+            Some((first, format)) =>
+            {
+                let kind = format.kind;
+
+                let mut formats = vec![first];
+                formats.extend(matching.filter(|(_, f)| f.kind == kind).map(|(i, _)| i));
+
+                FieldKind::Temporal { formats }
+            }
+
+            None => FieldKind::Factor,
+        }
+    }
+}
+
+/// A running summary of the values seen in one column; enough to decide its
+/// type without holding on to the values themselves.
+///
+/// For example, after `4.4`, `yes`, `1` and `` a column's summary has
+/// `empty: true`, `booleans: 2` (`yes` and `1`), `present: 3`, `integers: 1`,
+/// `reals: 1` and `texts: 1` (`yes`, as it isn't a number), and `4.4`, `yes`
+/// and `1` as its levels.
+#[derive(Debug, Default)]
+struct Summary
+{
+    // whether any values were empty, and whether any of those were spelled
+    // like `NA` rather than being blank
+    empty: bool,
+    nulls: bool,
+
+    // whether some of the inputs didn't have this column at all
+    missing: bool,
+
+    // how many values weren't
+    present: usize,
+
+    // how many values were boolean, and whether any of those were spelled
+    // other than `true` or `false`
+    booleans: usize,
+    nonstandard_booleans: bool,
+
+    // how many values were integers, and their range
+    integers: usize,
+    min: i128,
+    max: i128,
+
+    // how many values were reals
+    reals: usize,
+
+    // whether any of the numbers had formatting that had to be stripped off
+    formatted: bool,
+
+    // the fewest and most decimal places the numbers were written with;
+    // `None` if there weren't any, or any were written in a way `Decimal`
+    // can't read
+    scale: Option<(usize, usize)>,
+
+    // the float type needed for all of the numbers
+    precision: Option<Precision>,
+
+    // how many values weren't numbers, and the date formats that all of those
+    // match; `None` until the first one
+    texts: usize,
+    formats: Option<Vec<usize>>,
+
+    // the first few distinct values, as written, in the order they were first
+    // seen, and how many times each was seen; `level_index` maps the values to
+    // their position in `levels`. These are all of the values, not just those
+    // that weren't numbers, so that if the column is a factor then its enum
+    // can read every value in it
+    levels: Vec<(String, usize)>,
+    level_index: HashMap<String, usize>,
+}
+
+impl Summary
+{
+    /// Adds a value, given what it looks like and how it was written.
+    fn add(&mut self, kind: FieldKind, value: &str, opts: &Options)
+    {
+        match kind
+        {
+            FieldKind::Empty => self.empty = true,
+
+            FieldKind::Null =>
+            {
+                self.empty = true;
+                self.nulls = true;
+            }
+
+            kind =>
+            {
+                self.add_level(value, opts);
+                self.add_present(kind, value, opts);
+            }
+        }
+    }
+
+    fn add_present(&mut self, kind: FieldKind, value: &str, opts: &Options)
+    {
+        match kind
+        {
+            // booleans are also read as whatever else they look like, in case
+            // the column turns out not to be boolean
+            FieldKind::Boolean =>
+            {
+                self.booleans += 1;
+                self.nonstandard_booleans |= value != "true" && value != "false";
+                self.add_value(FieldKind::parse_value(value, opts));
+            }
+
+            kind => self.add_value(kind),
+        }
+    }
+
+    fn add_value(&mut self, kind: FieldKind)
+    {
+        self.present += 1;
+
+        match kind
+        {
+            FieldKind::Integer(i) =>
+            {
+                if self.integers == 0
+                {
+                    self.min = i;
+                    self.max = i;
+                }
+
+                self.add_scale(Some(0));
+                self.integers += 1;
+                self.min = self.min.min(i);
+                self.max = self.max.max(i);
+                self.precision = self.precision.max(Some(Precision::of_integer(i)));
+            }
+
+            FieldKind::Real(p, scale) =>
+            {
+                self.add_scale(scale);
+                self.reals += 1;
+                self.precision = self.precision.max(Some(p));
+            }
+
+            FieldKind::Temporal { formats } =>
+            {
+                match self.formats
+                {
+                    Some(ref mut common) => common.retain(|i| formats.contains(i)),
+                    None => self.formats = Some(formats),
+                }
+
+                self.texts += 1;
+            }
+
+            FieldKind::Factor =>
+            {
+                self.formats = Some(Vec::new());
+                self.texts += 1;
+            }
+
+            FieldKind::Formatted(kind) =>
+            {
+                self.formatted = true;
+                self.add_value(*kind);
+            }
+
+            FieldKind::Boolean | FieldKind::Empty | FieldKind::Null =>
+                unreachable!("parse_value never returns these"),
+        }
+    }
+
+    /// Takes the number of decimal places of a number into account; this has
+    /// to be done before it's counted.
+    fn add_scale(&mut self, scale: Option<usize>)
+    {
+        self.scale = match (self.scale, scale)
+        {
+            (_, Some(s)) if self.integers + self.reals == 0 => Some((s, s)),
+            (Some((min, max)), Some(s)) => Some((min.min(s), max.max(s))),
+            _ => None,
+        };
+    }
+
+    fn add_level(&mut self, value: &str, opts: &Options)
+    {
+        match self.level_index.get(value)
+        {
+            Some(&i) => self.levels[i].1 += 1,
+
+            // once there are too many levels for an enum then we don't need
+            // to know about any more
+            None if self.levels.len() <= opts.max_levels =>
+            {
+                self.level_index.insert(value.to_string(), self.levels.len());
+                self.levels.push((value.to_string(), 1));
+            }
+
+            None => (),
+        }
+    }
+
+    /// Whether every value was boolean.
+    fn is_boolean(&self) -> bool
+    {
+        self.booleans > 0 && self.booleans == self.present
+    }
+
+    /// The first date format that every value matches, if there is one.
+    fn temporal_format(&self) -> Option<usize>
+    {
+        match self.formats
+        {
+            Some(ref formats) if self.texts == self.present => formats.first().copied(),
+            _ => None,
+        }
+    }
+
+    /// The distinct factor levels, and how many times each was seen.
+    fn levels(&self, order: LevelOrder) -> Vec<(&str, usize)>
+    {
+        let mut levels: Vec<(&str, usize)> =
+            self.levels.iter()
+            .map(|(level, count)| (level.as_str(), *count))
+            .collect();
+
+        // the sorts are stable, so ties stay in the order they were first seen
+        match order
+        {
+            LevelOrder::FirstSeen => (),
+            LevelOrder::Alphabetical => levels.sort_by(|a, b| a.0.cmp(b.0)),
+            LevelOrder::Frequency => levels.sort_by_key(|l| Reverse(l.1)),
+        }
+
+        levels
+    }
+}
+
+/// Keeps a running summary of the values seen for each field.
+///
+/// This is then used to generate the type definitions, along with the number
+/// of records seen, which tells us how many distinct levels a factor can have
+/// before it's better off as a string.
+#[derive(Debug)]
+struct Index
+{
+    inner: Vec<(String, Summary)>,
+    rows: usize,
+}
+
+impl Index
+{
+    fn new() -> Self
+    {
+        Self { inner: Vec::new(), rows: 0 }
+    }
+
+    /// The position of the summary for each of `headers`, adding summaries
+    /// for any that haven't been seen before. If a name is used more than once
+    /// then the nth column with that name goes with the nth summary with it.
+    fn positions(&mut self, headers: &[String]) -> Vec<usize>
+    {
+        let mut positions = Vec::with_capacity(headers.len());
+
+        for (i, header) in headers.iter().enumerate()
+        {
+            let nth =
+                headers[..i].iter()
+                .filter(|h| *h == header)
+                .count();
+
+            let position =
+                self.inner.iter()
+                .enumerate()
+                .filter(|(_, (name, _))| name == header)
+                .map(|(position, _)| position)
+                .nth(nth);
+
+            let position = match position
+            {
+                Some(position) => position,
+
+                None =>
+                {
+                    self.inner.push((header.clone(), Summary::default()));
+                    self.inner.len() - 1
+                }
+            };
+
+            positions.push(position);
+        }
+
+        positions
+    }
+
+    /// Makes the columns that are missing from some of the inputs optional,
+    /// given the headers of each input. Returns the columns that were missing
+    /// along with the positions of the inputs they were missing from.
+    fn unify(&mut self, inputs: &[Vec<String>]) -> Vec<(String, Vec<usize>)>
+    {
+        let mut missing = Vec::new();
+
+        for i in 0..self.inner.len()
+        {
+            let name = &self.inner[i].0;
+
+            // as in `positions`, this is the nth column with this name
+            let nth =
+                self.inner[..i].iter()
+                .filter(|(n, _)| n == name)
+                .count();
+
+            let lacking: Vec<usize> =
+                inputs.iter()
+                .enumerate()
+                .filter(|(_, headers)| headers.iter().filter(|h| *h == name).count() <= nth)
+                .map(|(j, _)| j)
+                .collect();
+
+            if !lacking.is_empty()
+            {
+                missing.push((name.clone(), lacking));
+                self.inner[i].1.empty = true;
+                self.inner[i].1.missing = true;
+            }
+        }
+
+        missing
+    }
+
+    /// The schema for the index, with a struct called `name`.
+    fn to_schema(&self, name: &str, warnings: Vec<String>, opts: &Options) -> Schema
+    {
+        let columns =
+            self.inner.iter()
+            .map(|(header, summary)| self.to_column(header, summary, opts))
+            .collect();
+
+        Schema
+        {
+            name: name.to_string(),
+            columns,
+            headerless: !opts.dialect.header,
+            warnings,
+        }
+    }
+
+    /// Decides what a column is from its summary.
+    fn to_column(&self, header: &str, summary: &Summary, opts: &Options) -> Column
+    {
+        // if all are boolean -> bool
+        // else if all are dates or times with a format in common -> that
+        // else if any are factor -> factor, or string if there are too
+        // many distinct levels
+        // else if not all are integer -> real, f64 if any value needs it
+        // else -> narrowest integer type that holds the observed range
+        //
+        // if any are empty, then it's Option of the above
+
+        let test_boolean = summary.is_boolean();
+        let test_temporal = summary.temporal_format().filter(|_| !test_boolean);
+        let test_factor = summary.texts > 0;
+        let test_real = summary.reals > 0;
+
+        // money, or anything else whose numbers all have the same number of
+        // decimal places, can be a `Decimal` instead of a float
+        let test_decimal =
+            summary.scale
+            .filter(|&(min, max)| (min == max && min > 0) || number::is_money(header))
+            .filter(|_| opts.decimal && test_real);
+
+        // the timestamp modules only know about empty values, and plain
+        // numbers
+        let test_epoch =
+            Epoch::detect(header, summary.min, summary.max)
+            .filter(|_| opts.epochs && !summary.nulls && !summary.formatted);
+
+        let kind = if test_boolean
+        {
+            ColumnKind::Boolean { nonstandard: summary.nonstandard_booleans }
+        }
+        else if let Some(i) = test_temporal
+        {
+            ColumnKind::Temporal(opts.date_formats[i].clone())
+        }
+        else if test_factor && !self.is_enumerable(summary, opts)
+        {
+            ColumnKind::Text
+        }
+        else if test_factor
+        {
+            ColumnKind::Factor(
+                summary.levels(opts.level_order).iter()
+                .map(|(level, _)| level.to_string())
+                .collect()
+            )
+        }
+        else if let Some(scale) = test_decimal
+        {
+            ColumnKind::Decimal { scale }
+        }
+        else if test_real
+        {
+            ColumnKind::Real(summary.precision.unwrap_or(Precision::Single))
+        }
+        else if let Some(epoch) = test_epoch
+        {
+            ColumnKind::Timestamp(epoch)
+        }
+        else
+        {
+            ColumnKind::Integer { min: summary.min, max: summary.max }
+        };
+
+        Column
+        {
+            name: header.to_string(),
+            kind,
+            optional: summary.empty,
+            missing: summary.missing,
+            nulls: summary.nulls,
+            formatted: summary.formatted && !test_factor,
+        }
+    }
+
+    /// Whether a factor column has few enough distinct levels to be an enum.
+    fn is_enumerable(&self, summary: &Summary, opts: &Options) -> bool
+    {
+        let levels = summary.levels.len();
+
+        levels <= opts.max_levels
+        && levels as f64 <= opts.max_level_ratio * self.rows as f64
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// The kind of each column of `csv`.
+    fn kinds(csv: &str, opts: &Options) -> Vec<ColumnKind>
+    {
+        crate::infer(csv.as_bytes(), opts).unwrap()
+        .columns.into_iter()
+        .map(|c| c.kind)
+        .collect()
+    }
+
+    /// The levels of the first column of `csv`, which must be a factor.
+    fn levels(csv: &str, opts: &Options) -> Vec<String>
+    {
+        match kinds(csv, opts).remove(0)
+        {
+            ColumnKind::Factor(levels) => levels,
+            other => panic!("expected a factor, not {:?}", other),
+        }
+    }
+
+    fn warnings(csv: &str, header: bool) -> Vec<String>
+    {
+        let mut opts = Options::default();
+        opts.dialect.header = header;
+
+        let mut inference = Inference::new(&opts);
+        inference.read(csv.as_bytes(), "test.csv").unwrap();
+        inference.warnings
+    }
+
+    #[test]
+    fn warns_when_the_header_looks_like_data()
+    {
+        let warnings = warnings("1,2.5\n3,4.5\n", true);
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("looks like data rather than a header"));
+    }
+
+    #[test]
+    fn warns_when_the_first_row_looks_like_a_header()
+    {
+        let warnings = warnings("id,price\n1,2.5\n3,4.5\n", false);
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("looks like a header rather than data"));
+    }
+
+    #[test]
+    fn no_warning_when_the_header_is_right()
+    {
+        assert!(warnings("id,price\n1,2.5\n3,4.5\n", true).is_empty());
+        assert!(warnings("1,2.5\n3,4.5\n", false).is_empty());
+    }
+
+    #[test]
+    fn levels_are_emitted_once()
+    {
+        let opts = Options::default();
+
+        assert_eq!(levels("state\nopen\nshut\nopen\nshut\nopen\n", &opts), ["open", "shut"]);
+    }
+
+    #[test]
+    fn level_orders()
+    {
+        // `d` is the most common, and `b` and `c` tie
+        let csv = "x\nb\nc\na\nd\nb\nc\nd\nd\n";
+        let mut opts = Options::default();
+
+        assert_eq!(levels(csv, &opts), ["b", "c", "a", "d"]);
+
+        opts.level_order = LevelOrder::Alphabetical;
+        assert_eq!(levels(csv, &opts), ["a", "b", "c", "d"]);
+
+        opts.level_order = LevelOrder::Frequency;
+        assert_eq!(levels(csv, &opts), ["d", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn numbers_are_levels_in_a_mixed_column()
+    {
+        let opts = Options::default();
+
+        assert_eq!(levels("code\n1\nx\n2\nx\n1\nx\n2\nx\n", &opts), ["1", "x", "2"]);
+    }
+
+    #[test]
+    fn duplicate_headers_go_nth_to_nth()
+    {
+        let opts = Options::default();
+        let mut inference = Inference::new(&opts);
+
+        inference.read("a,b,a\n1,x,2.5\n3,y,4.5\n".as_bytes(), "one.csv").unwrap();
+        inference.read("a,a\n5,6.5\n".as_bytes(), "two.csv").unwrap();
+
+        let schema = inference.finish("Record");
+        let names: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+
+        assert_eq!(names, ["a", "b", "a"]);
+        assert!(matches!(schema.columns[0].kind, ColumnKind::Integer { min: 1, max: 5 }));
+        assert!(matches!(schema.columns[2].kind, ColumnKind::Real(_)));
+        assert!(schema.columns[1].missing);
+    }
+
+    #[test]
+    fn levels_stop_after_one_too_many()
+    {
+        let opts = Options { max_levels: 4, ..Options::default() };
+
+        let values: Vec<String> = (0..20).map(|i| format!("v{}", i)).collect();
+        let csv = format!("x\n{}\n", values.join("\n"));
+
+        let mut inference = Inference::new(&opts);
+        inference.read(csv.as_bytes(), "test.csv").unwrap();
+
+        let levels = &inference.index.inner[0].1.levels;
+
+        assert_eq!(levels.len(), 5);
+        assert_eq!(levels[4], ("v4".to_string(), 1));
+    }
+
+    #[test]
+    fn booleans()
+    {
+        let opts = Options::default();
+
+        assert!(matches!(kinds("x\nyes\nno\n", &opts)[0], ColumnKind::Boolean { nonstandard: true }));
+        assert!(matches!(kinds("x\ntrue\nfalse\n", &opts)[0], ColumnKind::Boolean { nonstandard: false }));
+        assert!(matches!(kinds("x\n0\n1\n2\n", &opts)[0], ColumnKind::Integer { min: 0, max: 2 }));
+        assert_eq!(levels("x\nyes\nno\nmaybe\nyes\nno\nmaybe\n", &opts), ["yes", "no", "maybe"]);
+    }
+
+    #[test]
+    fn custom_booleans()
+    {
+        let opts = Options
+        {
+            true_values: vec!["oui".to_string()],
+            false_values: vec!["non".to_string()],
+            ..Options::default()
+        };
+
+        assert!(matches!(kinds("x\noui\nnon\n", &opts)[0], ColumnKind::Boolean { nonstandard: true }));
+
+        // the values given replace the defaults
+        assert_eq!(levels("x\nyes\nno\nyes\nno\n", &opts), ["yes", "no"]);
+    }
+
+    /// A column of `rows` values, cycling through `levels` distinct ones.
+    fn cycle(levels: usize, rows: usize) -> String
+    {
+        let values: Vec<String> = (0..rows).map(|i| format!("v{}", i % levels)).collect();
+        format!("x\n{}\n", values.join("\n"))
+    }
+
+    #[test]
+    fn too_many_levels_is_text()
+    {
+        let opts = Options { max_levels: 3, ..Options::default() };
+
+        assert!(matches!(kinds(&cycle(3, 100), &opts)[0], ColumnKind::Factor(_)));
+        assert!(matches!(kinds(&cycle(4, 100), &opts)[0], ColumnKind::Text));
+    }
+
+    #[test]
+    fn too_many_levels_for_the_rows_is_text()
+    {
+        let mut opts = Options::default();
+
+        // at most half as many levels as rows, by default
+        assert!(matches!(kinds(&cycle(4, 8), &opts)[0], ColumnKind::Factor(_)));
+        assert!(matches!(kinds(&cycle(4, 7), &opts)[0], ColumnKind::Text));
+
+        opts.max_level_ratio = 1.0;
+        assert!(matches!(kinds(&cycle(4, 4), &opts)[0], ColumnKind::Factor(_)));
+    }
+
+    #[test]
+    fn columns_missing_from_some_inputs_are_optional()
+    {
+        let opts = Options::default();
+        let mut inference = Inference::new(&opts);
+
+        inference.read("id,price\n1,2.5\n".as_bytes(), "one.csv").unwrap();
+        inference.read("id\n2\n".as_bytes(), "two.csv").unwrap();
+
+        let schema = inference.finish("Record");
+
+        assert!(!schema.columns[0].optional && !schema.columns[0].missing);
+        assert!(schema.columns[1].optional && schema.columns[1].missing);
+        assert_eq!(schema.warnings, ["column \"price\" is missing from two.csv, so it is optional"]);
+    }
+
+    #[test]
+    fn integers_and_reals_in_different_inputs_are_real()
+    {
+        let opts = Options::default();
+        let mut inference = Inference::new(&opts);
+
+        inference.read("x\n1\n2\n".as_bytes(), "one.csv").unwrap();
+        inference.read("x\n2.5\n".as_bytes(), "two.csv").unwrap();
+
+        let schema = inference.finish("Record");
+
+        assert!(matches!(schema.columns[0].kind, ColumnKind::Real(Precision::Single)));
+        assert!(schema.warnings.is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+//! Finding the CSV files named on the command line.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Result};
+
+/// Extensions of the files we pick up when searching directories.
+const EXTENSIONS: &[&str] = &["csv", "tsv"];
+
+/// Somewhere to read CSV from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input
+{
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input
+{
+    pub fn open(&self) -> Result<Box<dyn Read>>
+    {
+        match self
+        {
+            Input::Stdin => Ok(Box::new(io::stdin())),
+
+            Input::File(path) =>
+                File::open(path)
+                .map(|f| Box::new(f) as Box<dyn Read>)
+                .map_err(|e| Error::Msg(format!("{}: {}", path.display(), e))),
+        }
+    }
+
+    /// The name of the file without its extension, if this is a file.
+    pub fn stem(&self) -> Option<String>
+    {
+        match self
+        {
+            Input::Stdin => None,
+            Input::File(path) => path.file_stem().map(|s| s.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+impl fmt::Display for Input
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Input::Stdin => write!(f, "<stdin>"),
+            Input::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Turns the command line arguments into a list of inputs.
+///
+/// `-` is stdin, which can only be given once, patterns containing `*`, `?` or `[` are expanded as globs, and
+/// directories are searched recursively for `.csv` and `.tsv` files. With no
+/// arguments at all we read stdin.
+pub fn expand(args: &[String]) -> Result<Vec<Input>>
+{
+    if args.is_empty()
+    {
+        return Ok(vec![Input::Stdin]);
+    }
+
+    let mut inputs = Vec::new();
+
+    for arg in args.iter()
+    {
+        if arg == "-"
+        {
+            // the first read would leave nothing for the second
+            if inputs.contains(&Input::Stdin)
+            {
+                return Err(Error::Msg("stdin (`-`) can only be read once".to_string()));
+            }
+
+            inputs.push(Input::Stdin);
+        }
+        else if arg.contains(&['*', '?', '['][..])
+        {
+            let mut matched = false;
+
+            for path in glob::glob(arg)?
+            {
+                matched = true;
+                add_path(&mut inputs, path?)?;
+            }
+
+            if !matched
+            {
+                return Err(Error::Msg(format!("{}: no files match", arg)));
+            }
+        }
+        else
+        {
+            add_path(&mut inputs, PathBuf::from(arg))?;
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// Adds a file, or the CSV files under a directory.
+fn add_path(inputs: &mut Vec<Input>, path: PathBuf) -> Result<()>
+{
+    if path.is_dir()
+    {
+        let mut entries =
+            fs::read_dir(&path)
+            .map_err(|e| Error::Msg(format!("{}: {}", path.display(), e)))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        // so the output doesn't depend on the order the filesystem gives us
+        entries.sort();
+
+        for entry in entries
+        {
+            if entry.is_dir() || has_csv_extension(&entry)
+            {
+                add_path(inputs, entry)?;
+            }
+        }
+    }
+    else
+    {
+        inputs.push(Input::File(path));
+    }
+
+    Ok(())
+}
+
+fn has_csv_extension(path: &Path) -> bool
+{
+    path.extension()
+    .and_then(|e| e.to_str())
+    .map(|e| EXTENSIONS.contains(&e.to_lowercase().as_str()))
+    .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::process;
+
+    use super::*;
+
+    /// A directory holding `files`, which can be in subdirectories.
+    fn temp_tree(name: &str, files: &[&str]) -> PathBuf
+    {
+        let dir = std::env::temp_dir().join(format!("csv2struct-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for file in files.iter()
+        {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "a\n1\n").unwrap();
+        }
+
+        dir
+    }
+
+    fn args(args: &[&str]) -> Vec<String>
+    {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn no_arguments_means_stdin()
+    {
+        assert_eq!(expand(&[]).unwrap(), [Input::Stdin]);
+        assert_eq!(expand(&args(&["-"])).unwrap(), [Input::Stdin]);
+    }
+
+    #[test]
+    fn stdin_can_only_be_read_once()
+    {
+        let e = expand(&args(&["-", "a.csv", "-"])).unwrap_err();
+
+        assert_eq!(e.to_string(), "stdin (`-`) can only be read once");
+    }
+
+    #[test]
+    fn globs_must_match_something()
+    {
+        let dir = temp_tree("glob-empty", &["a.csv"]);
+        let pattern = format!("{}/*.tsv", dir.display());
+
+        let e = expand(&args(&[&pattern])).unwrap_err();
+
+        assert_eq!(e.to_string(), format!("{}: no files match", pattern));
+    }
+
+    #[test]
+    fn globs_expand_to_their_matches()
+    {
+        let dir = temp_tree("glob", &["b.csv", "a.csv", "c.txt"]);
+        let pattern = format!("{}/*.csv", dir.display());
+
+        assert_eq!(
+            expand(&args(&[&pattern])).unwrap(),
+            [Input::File(dir.join("a.csv")), Input::File(dir.join("b.csv"))]
+        );
+    }
+
+    #[test]
+    fn directories_are_searched_in_order_for_csv_files()
+    {
+        let dir = temp_tree("dir", &["b.csv", "a/z.TSV", "a/notes.txt", "a/b/y.csv", "c", "README.md"]);
+
+        assert_eq!(
+            expand(&args(&[dir.to_str().unwrap()])).unwrap(),
+            [
+                Input::File(dir.join("a/b/y.csv")),
+                Input::File(dir.join("a/z.TSV")),
+                Input::File(dir.join("b.csv")),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_are_taken_whatever_their_extension()
+    {
+        let dir = temp_tree("file", &["data.txt"]);
+        let path = dir.join("data.txt");
+
+        assert_eq!(expand(&args(&["-", path.to_str().unwrap()])).unwrap(), [Input::Stdin, Input::File(path)]);
+    }
+}
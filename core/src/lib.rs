@@ -0,0 +1,428 @@
+//! # `csv2struct-core`
+//!
+//! The inference and code generation behind `csv2struct`. It's a crate of its
+//! own so that `csv2struct-macros` can use it too, and `csv2struct` can then
+//! re-export the macro; use it through `csv2struct` rather than directly.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::num::{ParseFloatError, ParseIntError};
+use std::path::Path;
+use std::process;
+
+mod builder;
+pub mod dialect;
+mod ident;
+mod infer;
+pub mod input;
+pub mod number;
+mod render;
+pub mod schema;
+pub mod temporal;
+
+pub use builder::Builder;
+pub use dialect::Dialect;
+pub use ident::struct_name;
+pub use infer::Inference;
+pub use input::Input;
+pub use number::NumberFormat;
+pub use schema::{Column, ColumnKind, Precision, Schema};
+pub use temporal::{Backend, Epoch, Format, TemporalKind};
+
+use render::Output;
+
+/// The values read as missing by default.
+pub const NULL_VALUES: &[&str] = &["NA", "N/A", "NULL", "null", "\\N", "-", "NaN"];
+/// The values read as `true` by default.
+pub const TRUE_VALUES: &[&str] = &["true", "True", "TRUE", "yes", "Yes", "YES", "y", "Y", "t", "T", "1"];
+/// The values read as `false` by default.
+pub const FALSE_VALUES: &[&str] = &["false", "False", "FALSE", "no", "No", "NO", "n", "N", "f", "F", "0"];
+
+/// Result alias.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Something that went wrong, with a message saying what.
+#[derive(Debug)]
+pub enum Error
+{
+    Msg(String),
+}
+
+impl fmt::Display for Error
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Error::Msg(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error
+{
+    fn from(e: io::Error) -> Self
+    {
+        Error::Msg(e.to_string())
+    }
+}
+
+impl From<csv::Error> for Error
+{
+    fn from(e: csv::Error) -> Self
+    {
+        Error::Msg(e.to_string())
+    }
+}
+
+impl From<glob::PatternError> for Error
+{
+    fn from(e: glob::PatternError) -> Self
+    {
+        Error::Msg(e.to_string())
+    }
+}
+
+impl From<glob::GlobError> for Error
+{
+    fn from(e: glob::GlobError) -> Self
+    {
+        Error::Msg(e.to_string())
+    }
+}
+
+impl From<ParseIntError> for Error
+{
+    fn from(e: ParseIntError) -> Self
+    {
+        Error::Msg(e.to_string())
+    }
+}
+
+impl From<ParseFloatError> for Error
+{
+    fn from(e: ParseFloatError) -> Self
+    {
+        Error::Msg(e.to_string())
+    }
+}
+
+
+/// How to read the CSV, and what code to generate for it.
+#[derive(Debug, Clone)]
+pub struct Options
+{
+    /// Whether to emit i64 instead of the narrowest integer type that fits.
+    pub always_i64: bool,
+
+    /// Whether to emit f64 even when every value fits in an f32.
+    pub always_f64: bool,
+
+    /// Whether integer columns that look like Unix timestamps become dates and
+    /// times.
+    pub epochs: bool,
+
+    /// How numbers are formatted.
+    pub number_format: NumberFormat,
+
+    /// Whether to use `Decimal` for money.
+    pub decimal: bool,
+
+    /// Whether factor enums get an `Other` variant for levels we haven't seen.
+    pub open_enums: bool,
+
+    /// Values other than an empty one that are read as missing.
+    pub null_values: Vec<String>,
+
+    /// Values that are read as `true`.
+    pub true_values: Vec<String>,
+
+    /// Values that are read as `false`.
+    pub false_values: Vec<String>,
+
+    /// The formats we look for dates and times in, in order of preference.
+    pub date_formats: Vec<Format>,
+
+    /// The crate whose types we use for dates and times.
+    pub backend: Backend,
+
+    /// Factors with more distinct levels than this become strings.
+    pub max_levels: usize,
+
+    /// Factors whose distinct levels are more than this fraction of the rows
+    /// become strings.
+    pub max_level_ratio: f64,
+
+    /// The order in which enum variants are generated.
+    pub level_order: LevelOrder,
+
+    /// Extra traits to derive on every generated type.
+    pub derives: Vec<String>,
+
+    /// Whether to derive serde's traits and generate what they need.
+    pub serde: bool,
+
+    /// How to read the CSV.
+    pub dialect: Dialect,
+
+    /// Names for the columns of inputs with no header.
+    pub names: Vec<String>,
+}
+
+impl Default for Options
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            always_i64: false,
+            always_f64: false,
+            epochs: true,
+            number_format: NumberFormat::Plain,
+            decimal: false,
+            open_enums: false,
+            null_values: NULL_VALUES.iter().map(|v| v.to_string()).collect(),
+            true_values: TRUE_VALUES.iter().map(|v| v.to_string()).collect(),
+            false_values: FALSE_VALUES.iter().map(|v| v.to_string()).collect(),
+            date_formats:
+                temporal::DEFAULT_FORMATS.iter()
+                .map(|f| Format::new(f).expect("built-in date formats are valid"))
+                .collect(),
+            backend: Backend::Chrono,
+            max_levels: 32,
+            max_level_ratio: 0.5,
+            level_order: LevelOrder::FirstSeen,
+            derives: Vec::new(),
+            serde: true,
+            dialect: Dialect::default(),
+            names: Vec::new(),
+        }
+    }
+}
+
+impl Options
+{
+    /// Whether `f` is one of the configured boolean values.
+    pub(crate) fn is_boolean(&self, f: &str) -> bool
+    {
+        self.true_values.iter().chain(self.false_values.iter()).any(|v| v == f)
+    }
+}
+
+
+/// The order in which the levels of a factor become enum variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelOrder
+{
+    FirstSeen,
+    Alphabetical,
+    Frequency,
+}
+
+/// Works out the schema of the CSV read from `reader`, with a struct called
+/// `Record`.
+pub fn infer<R: Read>(reader: R, options: &Options) -> Result<Schema>
+{
+    let mut inference = Inference::new(options);
+    inference.read(reader, "<input>")?;
+
+    Ok(inference.finish("Record"))
+}
+
+/// Works out the schemas for `inputs`, as the command line tool and `Builder`
+/// do. Normally that's one struct for all of them, called `name` or else
+/// `Record`, and they must have the same columns unless `merge` is set. With
+/// `per_file` there's a struct for each input instead, named after its file.
+pub fn infer_inputs(inputs: &[Input], name: Option<&str>, merge: bool, per_file: bool, options: &Options) -> Result<Vec<Schema>>
+{
+    if merge && per_file
+    {
+        return Err(Error::Msg("--merge and --per-file (`Builder::merge` and `Builder::per_file`) can't be used together".to_string()));
+    }
+
+    if per_file && name.is_some()
+    {
+        return Err(Error::Msg("a name can't be given when each struct is named after its file".to_string()));
+    }
+
+    let mut schemas = Vec::new();
+
+    if per_file
+    {
+        for input in inputs.iter()
+        {
+            let mut inference = Inference::new(options);
+            inference.read(input.open()?, &input.to_string())?;
+
+            let name = match input.stem()
+            {
+                Some(stem) => struct_name(&stem),
+                None => String::from("Record"),
+            };
+
+            schemas.push(inference.finish(&name));
+        }
+    }
+    else
+    {
+        // this is responsible for, for each field, keeping track of what type
+        // it looks like
+        let mut inference = Inference::new(options);
+        let mut headers = Vec::new();
+
+        for input in inputs.iter()
+        {
+            headers.push(inference.read(input.open()?, &input.to_string())?);
+
+            if !merge && headers[0] != headers[headers.len() - 1]
+            {
+                return Err(Error::Msg(format!(
+                    "{} has different columns to {}; they can be combined with --merge (or `Builder::merge`)",
+                    input, inputs[0]
+                )));
+            }
+        }
+
+        schemas.push(inference.finish(name.unwrap_or("Record")));
+    }
+
+    Ok(schemas)
+}
+
+/// Generates the struct for `schema`, along with the enums and helpers it
+/// needs.
+pub fn render(schema: &Schema, options: &Options) -> Result<String>
+{
+    render_all(std::slice::from_ref(schema), options)
+}
+
+/// Generates the structs for several schemas, which share enums and helpers
+/// where they can. Enums whose names are taken get the name of their struct
+/// as a prefix, even if it's `Record`.
+pub fn render_all(schemas: &[Schema], options: &Options) -> Result<String>
+{
+    let mut out = Output::new(schemas.len() > 1);
+
+    for schema in schemas.iter()
+    {
+        out.add_struct(schema, options)?;
+    }
+
+    out.render(options)
+}
+
+/// Writes `code` to `path`, unless the file already holds exactly that, in
+/// which case it's left alone so nothing that watches it is rebuilt. The code
+/// goes to a temporary file next to it first, which then replaces it, so the
+/// file is never seen half-written. Returns whether the file was written.
+pub fn write_if_changed<P: AsRef<Path>>(path: P, code: &str) -> Result<bool>
+{
+    let path = path.as_ref();
+    let context = |e: io::Error| Error::Msg(format!("{}: {}", path.display(), e));
+
+    if fs::read(path).ok().as_deref() == Some(code.as_bytes())
+    {
+        return Ok(false);
+    }
+
+    let name =
+        path.file_name()
+        .ok_or_else(|| Error::Msg(format!("{} isn't a file name", path.display())))?;
+
+    let mut temp = OsString::from(".");
+    temp.push(name);
+    temp.push(format!(".{}.tmp", process::id()));
+    let temp = path.with_file_name(temp);
+
+    fs::write(&temp, code).map_err(context)?;
+
+    fs::rename(&temp, path).map_err(|e|
+    {
+        let _ = fs::remove_file(&temp);
+        context(e)
+    })?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs::File;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    /// An empty directory for a test to use.
+    fn temp_dir(name: &str) -> PathBuf
+    {
+        let dir = std::env::temp_dir().join(format!("csv2struct-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// The names of the files in `dir`.
+    fn files(dir: &Path) -> Vec<OsString>
+    {
+        let mut files: Vec<OsString> =
+            fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn writes_new_and_changed_files()
+    {
+        let dir = temp_dir("write-changed");
+        let path = dir.join("out.rs");
+
+        assert!(write_if_changed(&path, "one").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one");
+
+        assert!(write_if_changed(&path, "two").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two");
+
+        assert_eq!(files(&dir), ["out.rs"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leaves_unchanged_files_alone()
+    {
+        let dir = temp_dir("write-unchanged");
+        let path = dir.join("out.rs");
+        fs::write(&path, "same").unwrap();
+
+        // wind the clock back, so a rewrite would show
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&path).unwrap().set_modified(then).unwrap();
+
+        assert!(!write_if_changed(&path, "same").unwrap());
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), then);
+
+        assert_eq!(files(&dir), ["out.rs"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cleans_up_when_it_fails()
+    {
+        let dir = temp_dir("write-fails");
+
+        // a file can't replace a directory
+        fs::create_dir(dir.join("out.rs")).unwrap();
+
+        assert!(write_if_changed(dir.join("out.rs"), "code").is_err());
+        assert_eq!(files(&dir), ["out.rs"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
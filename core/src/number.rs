@@ -0,0 +1,354 @@
+//! Numbers written for people rather than machines, like `$1,234.50`,
+//! `1.234,50 €`, `12 %` or `(45.00)`, and generation of the serde glue needed
+//! to read them.
+//!
+//! The formatting is stripped off to leave something `str::parse` can read:
+//! currency symbols, percent signs and spaces at either end, thousands
+//! separators, and accounting-style parentheses (which mean the number is
+//! negative). Percentages are kept as written, so `12 %` is `12`.
+
+use inflector::Inflector;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::render;
+use crate::{Error, Result};
+
+/// Defines an item for use here, along with a function giving its code for
+/// the generated helpers, so that the two can't disagree.
+macro_rules! shared
+{
+    ($(#[$attr:meta])* $code:ident => $($item:tt)*) =>
+    {
+        $(#[$attr])*
+        $($item)*
+
+        fn $code() -> TokenStream
+        {
+            quote!($($item)*)
+        }
+    };
+}
+
+shared!
+{
+    /// Symbols that can come before or after a number.
+    symbols_code =>
+    const SYMBOLS: &[char] = &['$', '€', '£', '¥', '₹', '%', ' '];
+}
+
+/// How numbers are formatted, which is to say which character separates the
+/// decimal part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFormat
+{
+    // only what `str::parse` understands
+    Plain,
+
+    // `1,234.50`
+    Dot,
+
+    // `1.234,50`
+    Comma,
+
+    // either, worked out for each value from the separators in it
+    Auto,
+}
+
+impl NumberFormat
+{
+    pub fn from_name(name: &str) -> Result<Self>
+    {
+        match name
+        {
+            "plain" => Ok(NumberFormat::Plain),
+            "dot" => Ok(NumberFormat::Dot),
+            "comma" => Ok(NumberFormat::Comma),
+            "auto" => Ok(NumberFormat::Auto),
+            other => Err(Error::Msg(format!("unknown number format: {}", other))),
+        }
+    }
+
+    /// The code for the function giving the decimal separator, which the
+    /// generated `strip_number` passes to `strip_with`.
+    fn decimal_code(self) -> TokenStream
+    {
+        match self
+        {
+            NumberFormat::Comma => quote!(|_| ','),
+            NumberFormat::Auto => quote!(guess_decimal),
+            _ => quote!(|_| '.'),
+        }
+    }
+}
+
+/// Strips the formatting from a number, or returns `None` if it isn't one.
+///
+/// The generated `strip_number` does the same, as it calls the same
+/// `strip_with`.
+pub(crate) fn strip(value: &str, format: NumberFormat) -> Option<String>
+{
+    let decimal: fn(&str) -> char = match format
+    {
+        NumberFormat::Plain => return None,
+        NumberFormat::Dot => |_| '.',
+        NumberFormat::Comma => |_| ',',
+        NumberFormat::Auto => guess_decimal,
+    };
+
+    strip_with(value, decimal)
+}
+
+shared!
+{
+    /// Strips the formatting from a number, given a function that works out
+    /// which character separates its decimal part.
+    strip_with_code =>
+    fn strip_with(value: &str, decimal: fn(&str) -> char) -> Option<String>
+    {
+        let mut value = value.trim();
+        let mut negative = false;
+
+        if let Some(inner) = value.strip_prefix('(').and_then(|v| v.strip_suffix(')'))
+        {
+            negative = true;
+            value = inner;
+        }
+
+        value = value.trim_matches(SYMBOLS);
+
+        if let Some(rest) = value.strip_prefix('-')
+        {
+            negative = !negative;
+            value = rest.trim_matches(SYMBOLS);
+        }
+
+        let decimal = decimal(value);
+        let separator = if decimal == '.' { ',' } else { '.' };
+
+        let (whole, fraction) = match value.split_once(decimal)
+        {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (value, None),
+        };
+
+        let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+
+        // thousands are separated by `separator` or a space, with the first
+        // group having up to three digits and the rest exactly three
+        let groups: Vec<&str> = whole.split([separator, ' ']).collect();
+
+        let grouped =
+            groups[1..].iter()
+            .all(|g| g.len() == 3)
+            && (groups.len() == 1 || groups[0].len() <= 3);
+
+        if !grouped || !groups.iter().all(|g| digits(g)) || fraction.is_some_and(|f| !digits(f))
+        {
+            return None;
+        }
+
+        let mut number = String::new();
+
+        if negative
+        {
+            number.push('-');
+        }
+
+        number.push_str(&groups.concat());
+
+        if let Some(fraction) = fraction
+        {
+            number.push('.');
+            number.push_str(fraction);
+        }
+
+        Some(number)
+    }
+}
+
+/// How many decimal places a number that `str::parse` understands was
+/// written with, or `None` if it has an exponent or isn't finite.
+pub(crate) fn scale(value: &str) -> Option<usize>
+{
+    if !value.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '-' || c == '+')
+    {
+        return None;
+    }
+
+    Some(value.split_once('.').map_or(0, |(_, fraction)| fraction.len()))
+}
+
+/// Whether a column's name suggests it holds amounts of money. The name is
+/// split into snake_case words, which are made singular and compared whole, so
+/// `unit_price` and `fees` are money but `feedback` isn't.
+pub(crate) fn is_money(name: &str) -> bool
+{
+    const WORDS: &[&str] =
+    &[
+        "price", "cost", "amount", "total", "balance", "fee", "salary", "wage",
+        "revenue", "income", "expense", "payment", "paid", "tax", "discount",
+        "charge", "refund", "money", "usd", "eur", "gbp",
+    ];
+
+    name.to_snake_case().split('_').any(|w| WORDS.contains(&w.to_singular().as_str()))
+}
+
+shared!
+{
+    /// Works out which character separates the decimal part of a number: the
+    /// last of `.` and `,` if it has both, or else `.` unless there's a `,`
+    /// that doesn't separate thousands.
+    guess_decimal_code =>
+    fn guess_decimal(value: &str) -> char
+    {
+        match (value.rfind('.'), value.rfind(','))
+        {
+            (Some(dot), Some(comma)) if comma > dot => ',',
+            (None, Some(_)) if !value.split(',').skip(1).all(|g| g.len() == 3) => ',',
+            _ => '.',
+        }
+    }
+}
+
+/// Generates `strip_number`, and the deserializers for plain and optional
+/// fields that use it. If `nulls` is set, the `NULL_VALUES` are read as `None`
+/// too.
+pub(crate) fn helpers(format: NumberFormat, plain: bool, optional: bool, nulls: bool) -> TokenStream
+{
+    let mut out = TokenStream::new();
+
+    if format == NumberFormat::Auto
+    {
+        out.extend(guess_decimal_code());
+    }
+
+    let decimal = format.decimal_code();
+
+    out.extend(symbols_code());
+    out.extend(strip_with_code());
+
+    out.extend(quote!
+    {
+        fn strip_number(value: &str) -> Option<String> {
+            strip_with(value, #decimal)
+        }
+    });
+
+    let parse = quote!
+    {
+        strip_number(&value)
+            .and_then(|number| number.parse().ok())
+            .or_else(|| value.parse().ok())
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid number: {:?}", value)))
+    };
+
+    if plain
+    {
+        out.extend(quote!
+        {
+            pub fn deserialize_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+                T: std::str::FromStr,
+            {
+                let value: String = serde::Deserialize::deserialize(deserializer)?;
+                #parse
+            }
+        });
+    }
+
+    if optional
+    {
+        let missing = render::missing(nulls);
+
+        out.extend(quote!
+        {
+            pub fn deserialize_optional_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+                T: std::str::FromStr,
+            {
+                let value: String = serde::Deserialize::deserialize(deserializer)?;
+                if #missing {
+                    return Ok(None);
+                }
+                #parse.map(Some)
+            }
+        });
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn strips_thousands_separators()
+    {
+        assert_eq!(strip("1,234.50", NumberFormat::Dot).as_deref(), Some("1234.50"));
+        assert_eq!(strip("1.234,50", NumberFormat::Comma).as_deref(), Some("1234.50"));
+        assert_eq!(strip("1 234 567", NumberFormat::Dot).as_deref(), Some("1234567"));
+    }
+
+    #[test]
+    fn strips_symbols_and_signs()
+    {
+        assert_eq!(strip("(45.00)", NumberFormat::Dot).as_deref(), Some("-45.00"));
+        assert_eq!(strip("12 %", NumberFormat::Dot).as_deref(), Some("12"));
+        assert_eq!(strip("$-5", NumberFormat::Dot).as_deref(), Some("-5"));
+        assert_eq!(strip("1.234,50 €", NumberFormat::Comma).as_deref(), Some("1234.50"));
+    }
+
+    #[test]
+    fn rejects_misplaced_separators()
+    {
+        assert_eq!(strip("1,23", NumberFormat::Dot).as_deref(), None);
+        assert_eq!(strip("12,34,567", NumberFormat::Dot).as_deref(), None);
+        assert_eq!(strip("1.2.3", NumberFormat::Dot).as_deref(), None);
+        assert_eq!(strip("abc", NumberFormat::Dot).as_deref(), None);
+    }
+
+    #[test]
+    fn plain_strips_nothing()
+    {
+        assert_eq!(strip("1,234.50", NumberFormat::Plain).as_deref(), None);
+    }
+
+    #[test]
+    fn auto_guesses_the_decimal_separator()
+    {
+        assert_eq!(strip("1,234.50", NumberFormat::Auto).as_deref(), Some("1234.50"));
+        assert_eq!(strip("1.234,50", NumberFormat::Auto).as_deref(), Some("1234.50"));
+        assert_eq!(strip("1,000", NumberFormat::Auto).as_deref(), Some("1000"));
+        assert_eq!(strip("0,99", NumberFormat::Auto).as_deref(), Some("0.99"));
+    }
+
+    #[test]
+    fn scales()
+    {
+        assert_eq!(scale("1.50"), Some(2));
+        assert_eq!(scale("-3.125"), Some(3));
+        assert_eq!(scale("12"), Some(0));
+        assert_eq!(scale("1e6"), None);
+        assert_eq!(scale("inf"), None);
+        assert_eq!(scale("NaN"), None);
+    }
+
+    #[test]
+    fn money_names_are_whole_words()
+    {
+        for name in ["fees", "unit_price", "unitPrice", "TotalAmount", "tax", "usd"].iter()
+        {
+            assert!(is_money(name), "{}", name);
+        }
+
+        for name in ["feedback", "syntax_score", "costume", "neuron", "id"].iter()
+        {
+            assert!(!is_money(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,1230 @@
+//! Generating the code for one or more schemas.
+//!
+//! The code is built up as tokens, rather than text, so that names and string
+//! literals are always written correctly; then it's parsed and pretty-printed
+//! all at once.
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use crate::ident::{self, Namer};
+use crate::number;
+use crate::schema::{ColumnKind, Precision, Schema};
+use crate::temporal::{self, Format, TemporalKind};
+use crate::{Error, Options, Result};
+
+/// The generated code for one module, which may hold several structs.
+#[derive(Debug)]
+pub(crate) struct Output
+{
+    // the struct definitions
+    structs: Vec<TokenStream>,
+
+    // the enums: their names, the struct they were made for, their levels
+    // (sorted, so identical ones can be shared) and their definitions
+    enums: Vec<(String, String, Vec<String>, TokenStream)>,
+
+    // names already used by types
+    type_names: Namer,
+
+    // whether there are several structs, in which case enums whose names are
+    // taken get the name of their struct as a prefix, even if it's `Record`
+    several: bool,
+
+    // which of the boolean deserializers the fields refer to
+    bool_helpers: (bool, bool),
+
+    // whether any fields have null values, and which of the deserializers
+    // for them (by parsing, and from strings) the fields refer to
+    nulls: bool,
+    null_helpers: (bool, bool),
+
+    // which of the deserializers for formatted numbers the fields refer to
+    number_helpers: (bool, bool),
+
+    // the date formats the fields use, the names of their modules, and
+    // whether they are used for plain and optional fields
+    format_modules: Vec<(Format, String, bool, bool)>,
+
+    // the module that holds all of the above, named after the first struct so
+    // that code for different structs can sit side by side
+    helpers: String,
+}
+
+impl Output
+{
+    pub(crate) fn new(several: bool) -> Self
+    {
+        Self
+        {
+            structs: Vec::new(),
+            enums: Vec::new(),
+            type_names: Namer::reserving(ident::RESERVED_TYPES),
+            several,
+            bool_helpers: (false, false),
+            nulls: false,
+            null_helpers: (false, false),
+            number_helpers: (false, false),
+            format_modules: Vec::new(),
+            helpers: String::new(),
+        }
+    }
+
+    /// Adds a struct for `schema`, along with the enums and helpers it
+    /// needs.
+    pub(crate) fn add_struct(&mut self, schema: &Schema, opts: &Options) -> Result<()>
+    {
+        let struct_name = self.type_names.unique(schema.name.clone(), "");
+
+        if self.helpers.is_empty()
+        {
+            self.helpers = format!("{}_serde", ident::unraw(&ident::field_name(&struct_name)));
+        }
+
+        let struct_ident: Ident =
+            syn::parse_str(&struct_name)
+            .map_err(|_| Error::Msg(format!("{:?} isn't a valid struct name", struct_name)))?;
+
+        let duplicates =
+            schema.columns.iter().enumerate()
+            .any(|(i, column)| schema.columns[..i].iter().any(|c| c.name == column.name));
+
+        // the fields of the struct, and what its fields let it derive
+        let mut fields = Vec::new();
+        let mut traits = Traits::ALL;
+
+        // names already used by fields
+        let mut field_names = Namer::default();
+
+        for column in schema.columns.iter()
+        {
+            let header = &column.name;
+            let name = field_names.unique(ident::field_name(header), "_");
+            let test_empty = column.optional;
+
+            let mut attrs = Vec::new();
+            let mut doc = None;
+
+            let mut ty = match &column.kind
+            {
+                ColumnKind::Boolean { nonstandard } =>
+                {
+                    traits = traits.and(Traits::ALL);
+
+                    // serde can only read `true` and `false` by itself
+                    if (*nonstandard || column.nulls) && test_empty
+                    {
+                        attrs.push(self.deserialize_with("deserialize_optional_bool"));
+                        self.bool_helpers.1 = true;
+                    }
+                    else if *nonstandard
+                    {
+                        attrs.push(self.deserialize_with("deserialize_bool"));
+                        self.bool_helpers.0 = true;
+                    }
+
+                    quote!(bool)
+                }
+
+                ColumnKind::Temporal(format) =>
+                {
+                    traits = traits.and(Traits::ALL);
+
+                    let same = |f: &Format| f.pattern == format.pattern && f.kind == format.kind;
+
+                    if !self.format_modules.iter().any(|(f, ..)| same(f))
+                    {
+                        let n =
+                            self.format_modules.iter()
+                            .filter(|(f, ..)| f.kind == format.kind)
+                            .count();
+
+                        let name = temporal::module_name(format.kind, n);
+                        self.format_modules.push((format.clone(), name, false, false));
+                    }
+
+                    let (_, module, plain, optional) =
+                        self.format_modules.iter_mut()
+                        .find(|(f, ..)| same(f))
+                        .unwrap();
+
+                    if test_empty
+                    {
+                        let module = format!("{}::{}::option", self.helpers, module);
+                        attrs.push(quote!(#[serde(with = #module)]));
+                        *optional = true;
+                    }
+                    else
+                    {
+                        let module = format!("{}::{}", self.helpers, module);
+                        attrs.push(quote!(#[serde(with = #module)]));
+                        *plain = true;
+                    }
+
+                    code(opts.backend.rust_type(format.kind))
+                }
+
+                ColumnKind::Text =>
+                {
+                    traits = traits.and(Traits::STRING);
+                    quote!(String)
+                }
+
+                ColumnKind::Factor(levels) =>
+                {
+                    traits = match opts.open_enums
+                    {
+                        true => traits.and(Traits::STRING),
+                        false => traits.and(Traits::ALL),
+                    };
+
+                    code(&self.add_enum(&struct_name, &name, levels, opts)?)
+                }
+
+                &ColumnKind::Decimal { scale: (min, max) } =>
+                {
+                    traits = traits.and(Traits::ALL);
+
+                    doc = Some(match (min, max)
+                    {
+                        (1, 1) => String::from(" Written with 1 decimal place."),
+                        _ if min == max => format!(" Written with {} decimal places.", max),
+                        _ => format!(" Written with {} to {} decimal places.", min, max),
+                    });
+
+                    // the helpers for formatted numbers and null values read
+                    // `Decimal`s too
+                    if !column.formatted && !column.nulls
+                    {
+                        let module = match test_empty
+                        {
+                            true => "rust_decimal::serde::str_option",
+                            false => "rust_decimal::serde::str",
+                        };
+
+                        attrs.push(quote!(#[serde(with = #module)]));
+                    }
+
+                    quote!(rust_decimal::Decimal)
+                }
+
+                ColumnKind::Real(precision) =>
+                {
+                    traits = traits.and(Traits::FLOAT);
+
+                    match precision
+                    {
+                        Precision::Single if !opts.always_f64 => quote!(f32),
+                        _ => quote!(f64),
+                    }
+                }
+
+                ColumnKind::Timestamp(epoch) =>
+                {
+                    traits = traits.and(Traits::ALL);
+
+                    let module = epoch.module(opts.backend, test_empty);
+                    attrs.push(quote!(#[serde(with = #module)]));
+
+                    code(opts.backend.rust_type(TemporalKind::DateTimeTz))
+                }
+
+                &ColumnKind::Integer { min, max } =>
+                {
+                    traits = traits.and(Traits::ALL);
+                    code(integer_type(min, max, opts.always_i64))
+                }
+            };
+
+            // numbers with formatting need a helper to strip it off
+            if column.formatted
+            {
+                if test_empty
+                {
+                    attrs.push(self.deserialize_with("deserialize_optional_number"));
+                    self.number_helpers.1 = true;
+                }
+                else
+                {
+                    attrs.push(self.deserialize_with("deserialize_number"));
+                    self.number_helpers.0 = true;
+                }
+            }
+
+            if test_empty
+            {
+                ty = quote!(Option<#ty>);
+            }
+
+            // the null values need a helper to read them, unless the field
+            // already has one
+            if column.nulls
+            {
+                self.nulls = true;
+
+                let text = matches!(column.kind, ColumnKind::Factor(_) | ColumnKind::Text);
+
+                if attrs.is_empty() && text
+                {
+                    attrs.push(self.deserialize_with("deserialize_optional_str"));
+                    self.null_helpers.1 = true;
+                }
+                else if attrs.is_empty()
+                {
+                    attrs.push(self.deserialize_with("deserialize_optional_parsed"));
+                    self.null_helpers.0 = true;
+                }
+            }
+
+            // serde only defaults missing fields to `None` by itself if it
+            // isn't using our helpers to read them
+            if column.missing && !attrs.is_empty()
+            {
+                attrs.push(quote!(#[serde(default)]));
+            }
+
+            // keep the original name for serde if we've had to change it
+            if ident::unraw(&name) != header
+            {
+                attrs.insert(0, quote!(#[serde(rename = #header)]));
+            }
+
+            if !opts.serde
+            {
+                attrs.clear();
+            }
+
+            let doc = doc.map(|doc| quote!(#[doc = #doc]));
+            let name = ident(&name);
+
+            fields.push(quote!
+            {
+                #doc
+                #(#attrs)*
+                pub #name: #ty
+            });
+        }
+
+        let doc = if schema.headerless
+        {
+            quote!
+            {
+                /// The source has no header row, so records need to be read with
+                /// `csv::ReaderBuilder::new().has_headers(false)`.
+            }
+        }
+        else if duplicates
+        {
+            quote!
+            {
+                /// Some of the columns in the source have the same name, so records
+                /// need to be read by position rather than by name; e.g. with
+                /// `csv::StringRecord::deserialize(&record, None)`.
+            }
+        }
+        else
+        {
+            quote!()
+        };
+
+        let derives = derives(traits, opts.serde, opts)?;
+
+        self.structs.push(quote!
+        {
+            #doc
+            #derives
+            pub struct #struct_ident
+            {
+                #(#fields,)*
+            }
+        });
+
+        Ok(())
+    }
+
+    /// The attribute that reads a field with one of the helper functions.
+    fn deserialize_with(&self, helper: &str) -> TokenStream
+    {
+        let path = format!("{}::{}", self.helpers, helper);
+        quote!(#[serde(deserialize_with = #path)])
+    }
+
+    /// Adds an enum for the factor column `field` of struct `struct_name`,
+    /// returning its name. If another struct already has an enum with the
+    /// same levels then that one is used instead.
+    fn add_enum(&mut self, struct_name: &str, field: &str, levels: &[String], opts: &Options) -> Result<String>
+    {
+        let mut sorted = levels.to_vec();
+        sorted.sort();
+
+        let shared =
+            self.enums.iter()
+            .find(|(_, owner, l, _)| owner != struct_name && *l == sorted);
+
+        if let Some((name, ..)) = shared
+        {
+            return Ok(name.clone());
+        }
+
+        let mut name = ident::type_name(field);
+
+        // enums for a struct that isn't just `Record` are named after it, so
+        // that code generated separately for different structs (as by
+        // `include_csv_struct!`) can sit side by side; otherwise they only are
+        // if the name is taken
+        let named = struct_name != "Record" && !name.starts_with(struct_name);
+
+        if named || (self.several && self.type_names.contains(&name))
+        {
+            name = format!("{}{}", struct_name, name);
+        }
+
+        let name = self.type_names.unique(name, "");
+
+        // open enums can't derive serde's traits, as those wouldn't know to
+        // use `Other` for anything else
+        let derives = match opts.open_enums
+        {
+            true => derives(Traits::STRING, false, opts)?,
+            false => derives(Traits::ALL, opts.serde, opts)?,
+        };
+
+        let mut variant_names = match opts.open_enums
+        {
+            true => Namer::reserving(&["Other"]),
+            false => Namer::default(),
+        };
+
+        let mut variants = Vec::new();
+        let mut defs = Vec::new();
+
+        for level in levels.iter()
+        {
+            let variant = variant_names.unique(ident::variant_name(level), "");
+
+            let rename = match variant != *level && opts.serde && !opts.open_enums
+            {
+                true => quote!(#[serde(rename = #level)]),
+                false => quote!(),
+            };
+
+            let variant = ident(&variant);
+
+            defs.push(quote!
+            {
+                #rename
+                #variant
+            });
+
+            variants.push((variant, level.as_str()));
+        }
+
+        let other = match opts.open_enums
+        {
+            true => quote!
+            {
+                /// Any other level.
+                Other(String),
+            },
+
+            false => quote!(),
+        };
+
+        let enum_ident = ident(&name);
+
+        let mut def = quote!
+        {
+            #derives
+            pub enum #enum_ident
+            {
+                #(#defs,)*
+                #other
+            }
+        };
+
+        if opts.open_enums
+        {
+            def.extend(open_enum_impls(&enum_ident, &variants, opts));
+        }
+
+        self.enums.push((name.clone(), struct_name.to_string(), sorted, def));
+        Ok(name)
+    }
+
+    /// All of the generated code, in order, formatted.
+    pub(crate) fn render(&self, opts: &Options) -> Result<String>
+    {
+        let mut code = TokenStream::new();
+
+        code.extend(self.structs.iter().cloned());
+        code.extend(self.enums.iter().map(|(.., def)| def.clone()));
+
+        // everything after here is only needed by serde
+        let mut helpers = TokenStream::new();
+
+        if opts.serde
+        {
+            if self.nulls
+            {
+                helpers.extend(null_helpers(opts, self.null_helpers.0, self.null_helpers.1));
+            }
+
+            if self.number_helpers != (false, false)
+            {
+                let (plain, optional) = self.number_helpers;
+                helpers.extend(number::helpers(opts.number_format, plain, optional, self.nulls));
+            }
+
+            if self.bool_helpers != (false, false)
+            {
+                helpers.extend(boolean_helpers(opts, self.bool_helpers.0, self.bool_helpers.1, self.nulls));
+            }
+
+            for (format, module, plain, optional) in self.format_modules.iter()
+            {
+                helpers.extend(format.module(module, opts.backend, *plain, *optional, self.nulls)?);
+            }
+        }
+
+        if !helpers.is_empty()
+        {
+            let name = ident(&self.helpers);
+
+            code.extend(quote!
+            {
+                /// What serde needs to read the fields above.
+                mod #name {
+                    #helpers
+                }
+            });
+        }
+
+        let file: syn::File =
+            syn::parse2(code)
+            .map_err(|e| Error::Msg(format!("generated code is invalid: {}", e)))?;
+
+        // print the items one at a time, so they can be separated by blank
+        // lines
+        let code =
+            file.items.into_iter()
+            .map(|item| prettyplease::unparse(&syn::File { shebang: None, attrs: Vec::new(), items: vec![item] }))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // make sure what we print reads back in
+        syn::parse_file(&code).map_err(|e| Error::Msg(format!("generated code doesn't parse: {}", e)))?;
+
+        Ok(code)
+    }
+}
+
+/// Tokens for a snippet of code that's known to be valid, like a type name.
+pub(crate) fn code(code: &str) -> TokenStream
+{
+    code.parse().expect("generated code is valid")
+}
+
+/// An identifier, which may be raw.
+pub(crate) fn ident(name: &str) -> Ident
+{
+    match name.strip_prefix("r#")
+    {
+        Some(raw) => Ident::new_raw(raw, Span::call_site()),
+        None => Ident::new(name, Span::call_site()),
+    }
+}
+
+/// Which of the optional standard traits a generated type can derive. Every
+/// type derives `Debug`, `Clone`, `PartialEq` and `PartialOrd`.
+#[derive(Debug, Clone, Copy)]
+struct Traits
+{
+    // `Copy`
+    copy: bool,
+
+    // `Eq`, `Ord` and `Hash`; floats have none of these
+    eq: bool,
+}
+
+impl Traits
+{
+    const ALL: Traits = Traits { copy: true, eq: true };
+    const FLOAT: Traits = Traits { copy: true, eq: false };
+    const STRING: Traits = Traits { copy: false, eq: true };
+
+    /// The traits a type with fields of both `self` and `other` can derive.
+    fn and(self, other: Traits) -> Traits
+    {
+        Traits
+        {
+            copy: self.copy && other.copy,
+            eq: self.eq && other.eq,
+        }
+    }
+}
+
+/// The derive attribute for a type, including any extra derives asked for,
+/// and serde's traits if `serde` is set.
+fn derives(traits: Traits, serde: bool, opts: &Options) -> Result<TokenStream>
+{
+    let mut names = vec!["Debug", "Clone"];
+
+    if traits.copy
+    {
+        names.push("Copy");
+    }
+
+    names.push("PartialEq");
+
+    if traits.eq
+    {
+        names.push("Eq");
+    }
+
+    names.push("PartialOrd");
+
+    if traits.eq
+    {
+        names.extend(&["Ord", "Hash"]);
+    }
+
+    if serde
+    {
+        names.extend(&["serde::Deserialize", "serde::Serialize"]);
+    }
+
+    for extra in opts.derives.iter()
+    {
+        if !names.contains(&extra.as_str())
+        {
+            names.push(extra);
+        }
+    }
+
+    let paths =
+        names.iter()
+        .map(|name| syn::parse_str::<syn::Path>(name).map_err(|_| Error::Msg(format!("can't derive {:?}", name))))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote!(#[derive(#(#paths),*)]))
+}
+
+/// Picks the narrowest integer type that can hold every value in `min..=max`.
+///
+/// Non-negative columns get an unsigned type. If `always_i64` is set then
+/// `i64` is used instead, unless the values do not fit in it.
+fn integer_type(min: i128, max: i128, always_i64: bool) -> &'static str
+{
+    const UNSIGNED: [(&str, i128); 4] =
+    [
+        ("u8",  u8::MAX as i128),
+        ("u16", u16::MAX as i128),
+        ("u32", u32::MAX as i128),
+        ("u64", u64::MAX as i128),
+    ];
+
+    const SIGNED: [(&str, i128, i128); 4] =
+    [
+        ("i8",  i8::MIN as i128,  i8::MAX as i128),
+        ("i16", i16::MIN as i128, i16::MAX as i128),
+        ("i32", i32::MIN as i128, i32::MAX as i128),
+        ("i64", i64::MIN as i128, i64::MAX as i128),
+    ];
+
+    if always_i64 && min >= i64::MIN as i128 && max <= i64::MAX as i128
+    {
+        return "i64";
+    }
+
+    if min >= 0
+    {
+        if let Some((name, _)) = UNSIGNED.iter().find(|(_, hi)| max <= *hi)
+        {
+            return name;
+        }
+    }
+
+    SIGNED.iter()
+    .find(|(_, lo, hi)| min >= *lo && max <= *hi)
+    .map(|(name, _, _)| *name)
+    .unwrap_or("i128")
+}
+
+/// Generates `as_str` and `FromStr` for an open enum, given its variants and
+/// the levels they stand for, and serde's traits in terms of those.
+fn open_enum_impls(name: &Ident, variants: &[(Ident, &str)], opts: &Options) -> TokenStream
+{
+    let idents: Vec<&Ident> = variants.iter().map(|(variant, _)| variant).collect();
+    let levels: Vec<&str> = variants.iter().map(|(_, level)| *level).collect();
+
+    let mut out = quote!
+    {
+        impl #name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    #(#name::#idents => #levels,)*
+                    #name::Other(other) => other,
+                }
+            }
+        }
+
+        impl std::str::FromStr for #name {
+            type Err = std::convert::Infallible;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                Ok(match value {
+                    #(#levels => #name::#idents,)*
+                    other => #name::Other(other.to_string()),
+                })
+            }
+        }
+    };
+
+    if opts.serde
+    {
+        out.extend(quote!
+        {
+            impl<'de> serde::Deserialize<'de> for #name {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    let value: String = serde::Deserialize::deserialize(deserializer)?;
+                    value.parse().map_err(serde::de::Error::custom)
+                }
+            }
+
+            impl serde::Serialize for #name {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer,
+                {
+                    serializer.serialize_str(self.as_str())
+                }
+            }
+        });
+    }
+
+    out
+}
+
+/// Generates the functions that let serde read the configured boolean values.
+///
+/// `plain` and `optional` select the deserializers for `bool` and
+/// `Option<bool>` fields respectively.
+fn boolean_helpers(opts: &Options, plain: bool, optional: bool, nulls: bool) -> TokenStream
+{
+    let true_values = &opts.true_values;
+    let false_values = &opts.false_values;
+
+    let mut out = quote!
+    {
+        fn parse_bool(value: &str) -> Option<bool> {
+            match value {
+                #(#true_values)|* => Some(true),
+                #(#false_values)|* => Some(false),
+                _ => None,
+            }
+        }
+    };
+
+    if plain
+    {
+        out.extend(quote!
+        {
+            pub fn deserialize_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value: String = serde::Deserialize::deserialize(deserializer)?;
+                parse_bool(&value)
+                    .ok_or_else(|| serde::de::Error::custom(format!("invalid boolean: {:?}", value)))
+            }
+        });
+    }
+
+    if optional
+    {
+        let missing = missing(nulls);
+
+        out.extend(quote!
+        {
+            pub fn deserialize_optional_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value: String = serde::Deserialize::deserialize(deserializer)?;
+                if #missing {
+                    return Ok(None);
+                }
+                parse_bool(&value)
+                    .map(Some)
+                    .ok_or_else(|| serde::de::Error::custom(format!("invalid boolean: {:?}", value)))
+            }
+        });
+    }
+
+    out
+}
+
+/// The condition under which a helper reads `value` as `None`: when it's
+/// empty, or if `nulls` is set, one of the `NULL_VALUES`.
+pub(crate) fn missing(nulls: bool) -> TokenStream
+{
+    match nulls
+    {
+        true => quote!(value.is_empty() || NULL_VALUES.contains(&value.as_str())),
+        false => quote!(value.is_empty()),
+    }
+}
+
+/// The values that mean a value is missing, and the deserializers for
+/// optional fields that can have them; one for types that can be parsed from
+/// strings, like numbers, and one for types that serde can read from strings,
+/// like enums.
+fn null_helpers(opts: &Options, parsed: bool, text: bool) -> TokenStream
+{
+    let values = &opts.null_values;
+
+    let mut out = quote!
+    {
+        const NULL_VALUES: &[&str] = &[#(#values),*];
+    };
+
+    if parsed
+    {
+        out.extend(quote!
+        {
+            pub fn deserialize_optional_parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+                T: std::str::FromStr,
+                T::Err: std::fmt::Display,
+            {
+                let value: String = serde::Deserialize::deserialize(deserializer)?;
+                if value.is_empty() || NULL_VALUES.contains(&value.as_str()) {
+                    return Ok(None);
+                }
+                value.parse().map(Some).map_err(serde::de::Error::custom)
+            }
+        });
+    }
+
+    if text
+    {
+        out.extend(quote!
+        {
+            pub fn deserialize_optional_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+                T: serde::Deserialize<'de>,
+            {
+                let value: String = serde::Deserialize::deserialize(deserializer)?;
+                if value.is_empty() || NULL_VALUES.contains(&value.as_str()) {
+                    return Ok(None);
+                }
+                T::deserialize(serde::de::value::StringDeserializer::<D::Error>::new(value)).map(Some)
+            }
+        });
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests
+{
+    use quote::ToTokens;
+
+    use super::*;
+
+    #[test]
+    fn unsigned_integer_boundaries()
+    {
+        assert_eq!(integer_type(0, 255, false), "u8");
+        assert_eq!(integer_type(0, 256, false), "u16");
+        assert_eq!(integer_type(0, u64::MAX as i128, false), "u64");
+        assert_eq!(integer_type(0, u64::MAX as i128 + 1, false), "i128");
+    }
+
+    #[test]
+    fn signed_integer_boundaries()
+    {
+        assert_eq!(integer_type(-128, 127, false), "i8");
+        assert_eq!(integer_type(-129, 127, false), "i16");
+        assert_eq!(integer_type(-1, 128, false), "i16");
+        assert_eq!(integer_type(i64::MIN as i128 - 1, 0, false), "i128");
+    }
+
+    #[test]
+    fn always_i64_unless_it_doesnt_fit()
+    {
+        assert_eq!(integer_type(0, 1, true), "i64");
+        assert_eq!(integer_type(-5, 5, true), "i64");
+        assert_eq!(integer_type(0, u64::MAX as i128, true), "u64");
+        assert_eq!(integer_type(i64::MIN as i128 - 1, 0, true), "i128");
+    }
+
+    /// The names of the items in `code`.
+    fn items(code: &str) -> Vec<String>
+    {
+        syn::parse_file(code).unwrap().items.iter()
+        .filter_map(|item| match item
+        {
+            syn::Item::Struct(s) => Some(s.ident.to_string()),
+            syn::Item::Enum(e) => Some(e.ident.to_string()),
+            syn::Item::Mod(m) => Some(m.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn separately_generated_structs_sit_side_by_side()
+    {
+        let opts = Options::default();
+        let csv = "id,state,price\n1,open,$1.50\n2,shut,NA\n3,open,$2.00\n4,shut,$2.50\n";
+
+        let mut code = String::new();
+
+        for name in ["Trade", "Order"].iter()
+        {
+            let mut schema = crate::infer(csv.as_bytes(), &opts).unwrap();
+            schema.name = name.to_string();
+            code.push_str(&crate::render(&schema, &opts).unwrap());
+        }
+
+        assert_eq!(items(&code), ["Trade", "TradeState", "trade_serde", "Order", "OrderState", "order_serde"]);
+    }
+
+    #[test]
+    fn record_enums_are_not_prefixed()
+    {
+        let opts = Options::default();
+        let schema = crate::infer("id,state\n1,open\n2,shut\n3,open\n4,shut\n".as_bytes(), &opts).unwrap();
+
+        assert_eq!(items(&crate::render(&schema, &opts).unwrap()), ["Record", "State"]);
+    }
+
+    /// The doc comment on each field of the first struct in `code`.
+    fn field_docs(code: &str) -> Vec<Option<String>>
+    {
+        let file = syn::parse_file(code).unwrap();
+
+        let fields = match &file.items[0]
+        {
+            syn::Item::Struct(s) => s.fields.clone(),
+            _ => panic!("expected a struct first"),
+        };
+
+        fields.iter()
+        .map(|field| field.attrs.iter().find_map(|attr| match &attr.meta
+        {
+            syn::Meta::NameValue(nv) if nv.path.is_ident("doc") => match &nv.value
+            {
+                syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }) => Some(s.value()),
+                _ => None,
+            },
+
+            _ => None,
+        }))
+        .collect()
+    }
+
+    #[test]
+    fn decimals_say_how_many_places()
+    {
+        let opts = Options { decimal: true, ..Options::default() };
+        let csv = "amount,price,rate,ratio\n1.5,1.5,0.125,0.5\n2.5,2.25,0.250,0.25\n";
+
+        let schema = crate::infer(csv.as_bytes(), &opts).unwrap();
+        let scales: Vec<_> =
+            schema.columns.iter()
+            .map(|c| match c.kind
+            {
+                ColumnKind::Decimal { scale } => Some(scale),
+                _ => None,
+            })
+            .collect();
+
+        // `rate` isn't money, but always has the same number of places
+        assert_eq!(scales, [Some((1, 1)), Some((1, 2)), Some((3, 3)), None]);
+
+        let docs = field_docs(&crate::render(&schema, &opts).unwrap());
+
+        assert_eq!(docs[0].as_deref(), Some(" Written with 1 decimal place."));
+        assert_eq!(docs[1].as_deref(), Some(" Written with 1 to 2 decimal places."));
+        assert_eq!(docs[2].as_deref(), Some(" Written with 3 decimal places."));
+        assert_eq!(docs[3], None);
+    }
+
+    /// A schema for `csv`, with a struct called `name`.
+    fn schema(name: &str, csv: &str, opts: &Options) -> Schema
+    {
+        let mut schema = crate::infer(csv.as_bytes(), opts).unwrap();
+        schema.name = name.to_string();
+        schema
+    }
+
+    /// The types of the fields of struct `name` in `code`.
+    fn field_types(code: &str, name: &str) -> Vec<String>
+    {
+        syn::parse_file(code).unwrap().items.iter()
+        .find_map(|item| match item
+        {
+            syn::Item::Struct(s) if s.ident == name =>
+                Some(s.fields.iter().map(|f| f.ty.to_token_stream().to_string()).collect()),
+            _ => None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn structs_share_enums_with_the_same_levels()
+    {
+        let opts = Options::default();
+
+        let schemas =
+        [
+            schema("Order", "status\nopen\nshut\nopen\nshut\n", &opts),
+            schema("Trade", "status\nshut\nopen\nshut\nopen\n", &opts),
+            schema("Customer", "status\ngold\nsilver\ngold\nsilver\n", &opts),
+        ];
+
+        let code = crate::render_all(&schemas, &opts).unwrap();
+
+        assert_eq!(items(&code), ["Order", "Trade", "Customer", "OrderStatus", "CustomerStatus"]);
+        assert_eq!(field_types(&code, "Trade"), ["OrderStatus"]);
+    }
+
+    #[test]
+    fn taken_enum_names_get_the_struct_name()
+    {
+        let opts = Options::default();
+
+        let schemas =
+        [
+            schema("Order", "order_status\nopen\nshut\nopen\nshut\n", &opts),
+            schema("Record", "order_status\ngold\nsilver\ngold\nsilver\n", &opts),
+        ];
+
+        let code = crate::render_all(&schemas, &opts).unwrap();
+
+        assert_eq!(field_types(&code, "Order"), ["OrderStatus"]);
+        assert_eq!(field_types(&code, "Record"), ["RecordOrderStatus"]);
+    }
+
+    /// The `deserialize_with` helper of each field of the first struct in
+    /// `code`.
+    fn deserializers(code: &str) -> Vec<Option<String>>
+    {
+        let file = syn::parse_file(code).unwrap();
+
+        let fields = match &file.items[0]
+        {
+            syn::Item::Struct(s) => s.fields.clone(),
+            _ => panic!("expected a struct first"),
+        };
+
+        fields.iter()
+        .map(|field| field.attrs.iter().find_map(|attr|
+        {
+            let attr = attr.to_token_stream().to_string();
+            let (_, path) = attr.split_once("deserialize_with = \"")?;
+            path.split('"').next().map(|path| path.to_string())
+        }))
+        .collect()
+    }
+
+    /// The names of the helpers in the module called `name` in `code`.
+    fn helpers(code: &str, name: &str) -> Vec<String>
+    {
+        let file = syn::parse_file(code).unwrap();
+
+        let items =
+            file.items.iter()
+            .find_map(|item| match item
+            {
+                syn::Item::Mod(m) if m.ident == name => m.content.as_ref().map(|(_, items)| items.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        items.iter()
+        .filter_map(|item| match item
+        {
+            syn::Item::Fn(f) => Some(f.sig.ident.to_string()),
+            syn::Item::Const(c) => Some(c.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn null_values_get_a_helper_for_their_kind()
+    {
+        let opts = Options::default();
+        let csv = "n,s,t,e\n1,a,t1,1\nNA,b,t2,\n3,NA,NA,3\n4,a,t4,4\n5,b,t5,5\n6,a,t6,6\n";
+
+        let code = crate::render(&schema("Record", csv, &opts), &opts).unwrap();
+
+        assert_eq!(field_types(&code, "Record"), ["Option < u8 >", "Option < S >", "Option < String >", "Option < u8 >"]);
+
+        assert_eq!(deserializers(&code),
+        [
+            Some("record_serde::deserialize_optional_parsed".to_string()),
+            Some("record_serde::deserialize_optional_str".to_string()),
+            Some("record_serde::deserialize_optional_str".to_string()),
+            None,
+        ]);
+
+        assert_eq!(helpers(&code, "record_serde"), ["NULL_VALUES", "deserialize_optional_parsed", "deserialize_optional_str"]);
+    }
+
+    #[test]
+    fn no_null_helpers_without_null_values()
+    {
+        let opts = Options::default();
+        let code = crate::render(&schema("Record", "n,s\n1,a\n,b\n3,\n4,a\n5,b\n", &opts), &opts).unwrap();
+
+        assert_eq!(deserializers(&code), [None, None]);
+        assert_eq!(items(&code), ["Record", "S"]);
+    }
+
+    #[test]
+    fn only_the_null_helpers_needed()
+    {
+        let opts = Options::default();
+        let code = crate::render(&schema("Record", "n\n1\nNA\n3\n", &opts), &opts).unwrap();
+
+        assert_eq!(helpers(&code, "record_serde"), ["NULL_VALUES", "deserialize_optional_parsed"]);
+    }
+
+    /// What the struct or enum called `name` in `code` derives.
+    fn derived(code: &str, name: &str) -> Vec<String>
+    {
+        let file = syn::parse_file(code).unwrap();
+
+        let attrs =
+            file.items.iter()
+            .find_map(|item| match item
+            {
+                syn::Item::Struct(s) if s.ident == name => Some(s.attrs.clone()),
+                syn::Item::Enum(e) if e.ident == name => Some(e.attrs.clone()),
+                _ => None,
+            })
+            .unwrap();
+
+        attrs.iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .flat_map(|attr|
+            attr.parse_args_with(syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated)
+            .unwrap()
+        )
+        .map(|path| path.to_token_stream().to_string().replace(' ', ""))
+        .collect()
+    }
+
+    /// The traits implemented by hand in `code`, or `""` for inherent impls.
+    fn impls(code: &str) -> Vec<String>
+    {
+        syn::parse_file(code).unwrap().items.iter()
+        .filter_map(|item| match item
+        {
+            syn::Item::Impl(i) => Some(i.trait_.as_ref().map_or(String::new(), |t| t.1.to_token_stream().to_string())),
+            _ => None,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn open_enums()
+    {
+        let opts = Options { open_enums: true, ..Options::default() };
+        let code = crate::render(&schema("Record", "s\nother\nOther\na\nother\nOther\na\n", &opts), &opts).unwrap();
+
+        let file = syn::parse_file(&code).unwrap();
+
+        let variants: Vec<String> = match &file.items[1]
+        {
+            syn::Item::Enum(e) => e.variants.iter().map(|v| v.ident.to_string()).collect(),
+            _ => panic!("expected an enum after the struct"),
+        };
+
+        // `Other` is kept for levels that weren't seen
+        assert_eq!(variants, ["Other2", "Other3", "A", "Other"]);
+
+        // not `Copy`, as `Other` holds a `String`, and serde's traits are
+        // implemented by hand in terms of `FromStr` and `as_str`
+        assert_eq!(derived(&code, "S"), ["Debug", "Clone", "PartialEq", "Eq", "PartialOrd", "Ord", "Hash"]);
+        assert!(!derived(&code, "Record").contains(&"Copy".to_string()));
+
+        assert_eq!(impls(&code), ["", "std :: str :: FromStr", "serde :: Deserialize < 'de >", "serde :: Serialize"]);
+
+        let opts = Options { serde: false, ..opts };
+        let code = crate::render(&schema("Record", "s\na\nb\na\nb\n", &opts), &opts).unwrap();
+
+        assert_eq!(impls(&code), ["", "std :: str :: FromStr"]);
+    }
+
+    #[test]
+    fn derives_what_the_fields_allow()
+    {
+        let opts = Options::default();
+        let serde = ["serde::Deserialize", "serde::Serialize"];
+
+        let code = crate::render(&schema("Record", "n,s\n1,a\n2,b\n3,c\n4,d\n", &opts), &opts).unwrap();
+        assert_eq!(field_types(&code, "Record"), ["u8", "String"]);
+        assert_eq!(derived(&code, "Record"), [&["Debug", "Clone", "PartialEq", "Eq", "PartialOrd", "Ord", "Hash"][..], &serde].concat());
+
+        let code = crate::render(&schema("Record", "n,r\n1,1.5\n2,2.5\n", &opts), &opts).unwrap();
+        assert_eq!(derived(&code, "Record"), [&["Debug", "Clone", "Copy", "PartialEq", "PartialOrd"][..], &serde].concat());
+
+        let code = crate::render(&schema("Record", "n\n1\n2\n", &opts), &opts).unwrap();
+        assert_eq!(derived(&code, "Record"), [&["Debug", "Clone", "Copy", "PartialEq", "Eq", "PartialOrd", "Ord", "Hash"][..], &serde].concat());
+    }
+
+    #[test]
+    fn extra_derives_are_added_once()
+    {
+        let opts = Options
+        {
+            derives: vec!["Default".to_string(), "Debug".to_string(), "Default".to_string(), "serde::Serialize".to_string()],
+            serde: false,
+            ..Options::default()
+        };
+
+        let code = crate::render(&schema("Record", "n\n1\n2\n", &opts), &opts).unwrap();
+
+        assert_eq!(
+            derived(&code, "Record"),
+            ["Debug", "Clone", "Copy", "PartialEq", "Eq", "PartialOrd", "Ord", "Hash", "Default", "serde::Serialize"]
+        );
+    }
+
+    #[test]
+    fn derives_must_be_paths()
+    {
+        let opts = Options { derives: vec!["not a trait".to_string()], ..Options::default() };
+
+        assert!(crate::render(&schema("Record", "n\n1\n2\n", &opts), &opts).is_err());
+    }
+
+    #[test]
+    fn no_serde_leaves_out_everything_serde_needs()
+    {
+        let csv = "Trade Id,state,ok,day,price\n1,open,yes,2024-01-02,1.5\n2,closed,no,NA,\n3,open,yes,2024-01-03,2.5\n4,closed,no,2024-01-04,3.5\n";
+
+        let opts = Options::default();
+        let code = crate::render(&schema("Record", csv, &opts), &opts).unwrap();
+        assert_eq!(items(&code), ["Record", "State", "record_serde"]);
+        assert!(code.contains("#[serde(rename = \"Trade Id\")]"), "{}", code);
+        assert!(deserializers(&code).iter().any(Option::is_some));
+
+        let opts = Options { serde: false, ..opts };
+        let code = crate::render(&schema("Record", csv, &opts), &opts).unwrap();
+
+        assert_eq!(items(&code), ["Record", "State"]);
+        assert!(!code.contains("serde"), "{}", code);
+    }
+}
@@ -0,0 +1,133 @@
+//! The public model of what we worked out about some CSV, from which the code
+//! is generated.
+
+use crate::temporal::{Epoch, Format};
+
+/// The columns of one or more inputs, and the name of the struct to generate
+/// for them.
+#[derive(Debug, Clone)]
+pub struct Schema
+{
+    /// The name of the struct.
+    pub name: String,
+
+    /// The columns, in order.
+    pub columns: Vec<Column>,
+
+    /// Whether the inputs had no header row, so records are read by position.
+    pub headerless: bool,
+
+    /// Anything about the inputs that the user should know, like columns that
+    /// some of them didn't have.
+    pub warnings: Vec<String>,
+}
+
+/// A column, which becomes a field of the struct.
+#[derive(Debug, Clone)]
+pub struct Column
+{
+    /// The header.
+    pub name: String,
+
+    /// What the values are.
+    pub kind: ColumnKind,
+
+    /// Whether any values were missing, so the field is an `Option`.
+    pub optional: bool,
+
+    /// Whether some of the inputs didn't have the column at all.
+    pub missing: bool,
+
+    /// Whether any of the missing values were spelled like `NA`, rather than
+    /// being empty.
+    pub nulls: bool,
+
+    /// Whether any of the numbers had formatting that has to be stripped off to
+    /// read them, like `$1,234.50`.
+    pub formatted: bool,
+}
+
+/// What the values in a column are.
+#[derive(Debug, Clone)]
+pub enum ColumnKind
+{
+    /// `true` and `false`, or other values that mean the same; `nonstandard` if
+    /// any were spelled differently.
+    Boolean
+    {
+        nonstandard: bool,
+    },
+
+    /// Dates or times in this format.
+    Temporal(Format),
+
+    /// Text with few enough distinct values to be an enum, and those values in
+    /// the order they become variants.
+    Factor(Vec<String>),
+
+    /// Text with too many distinct values to be an enum.
+    Text,
+
+    /// Amounts of money, with the fewest and most decimal places they were
+    /// written with.
+    Decimal
+    {
+        scale: (usize, usize),
+    },
+
+    /// Numbers that aren't all integers.
+    Real(Precision),
+
+    /// Integers that are Unix timestamps.
+    Timestamp(Epoch),
+
+    /// Integers in this range.
+    Integer
+    {
+        min: i128,
+        max: i128,
+    },
+}
+
+/// The float type needed to hold a real value without losing anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precision
+{
+    Single,
+    Double,
+}
+
+impl Precision
+{
+    /// Integers larger than this (in magnitude) can't all be represented
+    /// exactly by an `f32`.
+    const MAX_EXACT_F32: i128 = 1 << f32::MANTISSA_DIGITS;
+
+    /// `f32` is enough if converting the value to `f32` and printing it back
+    /// out gives the same number; i.e. it isn't too long or too large.
+    pub(crate) fn of(r: f64) -> Self
+    {
+        let single = r as f32;
+
+        if !r.is_finite() || single.to_string().parse::<f64>() == Ok(r)
+        {
+            Precision::Single
+        }
+        else
+        {
+            Precision::Double
+        }
+    }
+
+    pub(crate) fn of_integer(i: i128) -> Self
+    {
+        if i.unsigned_abs() <= Self::MAX_EXACT_F32 as u128
+        {
+            Precision::Single
+        }
+        else
+        {
+            Precision::Double
+        }
+    }
+}
@@ -0,0 +1,557 @@
+//! Date and time detection, and generation of the serde glue needed to read
+//! the detected formats.
+//!
+//! Values are matched against a list of `strftime` formats using `chrono`. A
+//! column is temporal if every value in it matches at least one format in
+//! common; the first such format is the one used in the generated code.
+
+use chrono::format::{Fixed, Item, Numeric, Pad, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use inflector::Inflector;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::render;
+use crate::{Error, Result};
+
+/// Formats tried when looking for dates and times, after any given with
+/// `--date-format`.
+pub const DEFAULT_FORMATS: &[&str] =
+&[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%d.%m.%Y",
+    "%m/%d/%Y",
+    "%d/%m/%Y",
+    "%H:%M:%S%.f",
+    "%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f%:z",
+    "%Y-%m-%dT%H:%M:%S%.f%:z",
+    "%Y-%m-%d %H:%M:%S%.fZ",
+    "%Y-%m-%dT%H:%M:%S%.fZ",
+];
+
+/// The kinds of date and time values we recognise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemporalKind
+{
+    Date,
+    Time,
+    DateTime,
+    DateTimeTz,
+}
+
+impl TemporalKind
+{
+    /// Used to name the generated format modules.
+    fn module_prefix(self) -> &'static str
+    {
+        match self
+        {
+            TemporalKind::Date => "date",
+            TemporalKind::Time => "time",
+            TemporalKind::DateTime => "datetime",
+            TemporalKind::DateTimeTz => "datetime_tz",
+        }
+    }
+}
+
+/// Which crate the generated code uses for dates and times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend
+{
+    Chrono,
+    Time,
+}
+
+impl Backend
+{
+    /// The generated type for values of the given kind.
+    pub fn rust_type(self, kind: TemporalKind) -> &'static str
+    {
+        match (self, kind)
+        {
+            (Backend::Chrono, TemporalKind::Date) => "chrono::NaiveDate",
+            (Backend::Chrono, TemporalKind::Time) => "chrono::NaiveTime",
+            (Backend::Chrono, TemporalKind::DateTime) => "chrono::NaiveDateTime",
+            (Backend::Chrono, TemporalKind::DateTimeTz) => "chrono::DateTime<chrono::Utc>",
+            (Backend::Time, TemporalKind::Date) => "time::Date",
+            (Backend::Time, TemporalKind::Time) => "time::Time",
+            (Backend::Time, TemporalKind::DateTime) => "time::PrimitiveDateTime",
+            (Backend::Time, TemporalKind::DateTimeTz) => "time::OffsetDateTime",
+        }
+    }
+}
+
+/// A `strftime` format that values can be matched against.
+#[derive(Debug, Clone)]
+pub struct Format
+{
+    /// The format as given.
+    pub pattern: String,
+
+    /// What the format describes.
+    pub kind: TemporalKind,
+
+    // whether the format ends with a literal `Z` rather than an offset; we
+    // read these as UTC
+    zulu: bool,
+}
+
+impl Format
+{
+    pub fn new(pattern: &str) -> Result<Self>
+    {
+        let mut date = false;
+        let mut time = false;
+        let mut offset = false;
+        let mut zulu = false;
+
+        for item in StrftimeItems::new(pattern)
+        {
+            zulu = false;
+
+            match item
+            {
+                Item::Numeric(Numeric::Year, _)
+                | Item::Numeric(Numeric::YearDiv100, _)
+                | Item::Numeric(Numeric::YearMod100, _)
+                | Item::Numeric(Numeric::Month, _)
+                | Item::Numeric(Numeric::Day, _)
+                | Item::Numeric(Numeric::Ordinal, _)
+                | Item::Fixed(Fixed::ShortMonthName)
+                | Item::Fixed(Fixed::LongMonthName) => date = true,
+
+                Item::Numeric(Numeric::Hour, _)
+                | Item::Numeric(Numeric::Hour12, _)
+                | Item::Numeric(Numeric::Minute, _)
+                | Item::Numeric(Numeric::Second, _) => time = true,
+
+                Item::Fixed(Fixed::TimezoneOffset)
+                | Item::Fixed(Fixed::TimezoneOffsetColon) => offset = true,
+
+                Item::Literal(s) => zulu = s.ends_with('Z'),
+
+                Item::Error =>
+                    return Err(Error::Msg(format!("invalid date format: {}", pattern))),
+
+                _ => (),
+            }
+        }
+
+        let kind = match (date, time, offset || zulu)
+        {
+            (true, true, true) => TemporalKind::DateTimeTz,
+            (true, true, false) => TemporalKind::DateTime,
+            (true, false, _) => TemporalKind::Date,
+            (false, true, _) => TemporalKind::Time,
+            (false, false, _) =>
+                return Err(Error::Msg(format!("not a date or time format: {}", pattern))),
+        };
+
+        Ok(Self
+        {
+            pattern: pattern.to_string(),
+            kind,
+            zulu: zulu && !offset,
+        })
+    }
+
+    /// Whether `value` can be read with this format.
+    pub fn matches(&self, value: &str) -> bool
+    {
+        let p = self.pattern.as_str();
+
+        match self.kind
+        {
+            TemporalKind::Date => NaiveDate::parse_from_str(value, p).is_ok(),
+            TemporalKind::Time => NaiveTime::parse_from_str(value, p).is_ok(),
+            TemporalKind::DateTime => NaiveDateTime::parse_from_str(value, p).is_ok(),
+            TemporalKind::DateTimeTz if self.zulu => NaiveDateTime::parse_from_str(value, p).is_ok(),
+            TemporalKind::DateTimeTz => DateTime::parse_from_str(value, p).is_ok(),
+        }
+    }
+
+    /// Generates a module for use with `#[serde(with = "...")]` that reads and
+    /// writes values in this format. If `optional` is set, an `option`
+    /// submodule does the same for optional fields; `plain` says whether the
+    /// module is used for non-optional fields. If `nulls` is set, the
+    /// `NULL_VALUES` next to the module are read as `None` too.
+    pub(crate) fn module(&self, name: &str, backend: Backend, plain: bool, optional: bool, nulls: bool) -> Result<TokenStream>
+    {
+        let name = render::ident(name);
+        let ty = render::code(backend.rust_type(self.kind));
+
+        let (consts, parse, format) = match backend
+        {
+            Backend::Chrono =>
+            {
+                let pattern = &self.pattern;
+                let consts = quote!(const FORMAT: &str = #pattern;);
+
+                let parse = match self.kind
+                {
+                    TemporalKind::DateTimeTz if self.zulu =>
+                        quote!(chrono::NaiveDateTime::parse_from_str(value, FORMAT).map(|dt| dt.and_utc())),
+                    TemporalKind::DateTimeTz =>
+                        quote!(chrono::DateTime::parse_from_str(value, FORMAT).map(|dt| dt.with_timezone(&chrono::Utc))),
+                    _ =>
+                        quote!(#ty::parse_from_str(value, FORMAT)),
+                };
+
+                (consts, parse, quote!(value.format(FORMAT).to_string()))
+            }
+
+            Backend::Time =>
+            {
+                let parse_format = self.time_description(true)?;
+                let format = self.time_description(false)?;
+
+                // the time crate needs the format descriptions parsing first
+                let consts = quote!
+                {
+                    const PARSE_FORMAT: &str = #parse_format;
+                    const FORMAT: &str = #format;
+
+                    fn description(format: &str) -> time::format_description::OwnedFormatItem {
+                        time::format_description::parse_owned::<2>(format).expect("valid format description")
+                    }
+                };
+
+                let parse = match self.kind
+                {
+                    TemporalKind::DateTimeTz if self.zulu =>
+                        quote!(time::PrimitiveDateTime::parse(value, &description(PARSE_FORMAT)).map(|dt| dt.assume_utc())),
+                    _ =>
+                        quote!(#ty::parse(value, &description(PARSE_FORMAT))),
+                };
+
+                (consts, parse, quote!(value.format(&description(FORMAT)).map_err(serde::ser::Error::custom)?))
+            }
+        };
+
+        let deserialize = match plain
+        {
+            true => quote!
+            {
+                pub fn deserialize<'de, D>(deserializer: D) -> Result<#ty, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    let value: String = serde::Deserialize::deserialize(deserializer)?;
+                    parse(&value).map_err(serde::de::Error::custom)
+                }
+            },
+
+            false => quote!(),
+        };
+
+        let option = match optional
+        {
+            true =>
+            {
+                let missing = match nulls
+                {
+                    true => quote!(value.is_empty() || super::super::NULL_VALUES.contains(&value.as_str())),
+                    false => quote!(value.is_empty()),
+                };
+
+                quote!
+                {
+                    pub mod option {
+                        pub fn serialize<S>(value: &Option<#ty>, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            S: serde::Serializer,
+                        {
+                            match value {
+                                Some(value) => super::serialize(value, serializer),
+                                None => serializer.serialize_none(),
+                            }
+                        }
+
+                        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<#ty>, D::Error>
+                        where
+                            D: serde::Deserializer<'de>,
+                        {
+                            let value: String = serde::Deserialize::deserialize(deserializer)?;
+                            if #missing {
+                                return Ok(None);
+                            }
+                            super::parse(&value).map(Some).map_err(serde::de::Error::custom)
+                        }
+                    }
+                }
+            }
+
+            false => quote!(),
+        };
+
+        Ok(quote!
+        {
+            pub mod #name {
+                #consts
+
+                fn parse(value: &str) -> Result<#ty, impl std::fmt::Display> {
+                    #parse
+                }
+
+                pub fn serialize<S>(value: &#ty, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer,
+                {
+                    serializer.serialize_str(&#format)
+                }
+
+                #deserialize
+
+                #option
+            }
+        })
+    }
+
+    /// Translates the format into a `time` format description.
+    ///
+    /// `chrono` accepts numbers without their leading zeroes, so when `lenient`
+    /// is set the description does too. Otherwise numbers are zero-padded, as
+    /// `chrono` would write them.
+    pub(crate) fn time_description(&self, lenient: bool) -> Result<String>
+    {
+        let mut out = String::new();
+
+        let padding = |pad: &Pad| match pad
+        {
+            _ if lenient => " padding:none",
+            Pad::Zero => "",
+            Pad::Space => " padding:space",
+            Pad::None => " padding:none",
+        };
+
+        // version 2 descriptions escape brackets and backslashes with a
+        // backslash
+        let literal = |s: &str| s.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]");
+
+        for item in StrftimeItems::new(&self.pattern)
+        {
+            let component = match item
+            {
+                Item::Literal(s) | Item::Space(s) => literal(s),
+                Item::OwnedLiteral(s) | Item::OwnedSpace(s) => literal(&s),
+
+                Item::Numeric(Numeric::Year, _) => "[year]".to_string(),
+                Item::Numeric(Numeric::YearMod100, pad) =>
+                    format!("[year repr:last_two{}]", padding(&pad)),
+                Item::Numeric(Numeric::Month, pad) => format!("[month{}]", padding(&pad)),
+                Item::Numeric(Numeric::Day, pad) => format!("[day{}]", padding(&pad)),
+                Item::Numeric(Numeric::Ordinal, pad) => format!("[ordinal{}]", padding(&pad)),
+                Item::Numeric(Numeric::Hour, pad) => format!("[hour{}]", padding(&pad)),
+                Item::Numeric(Numeric::Hour12, pad) =>
+                    format!("[hour repr:12{}]", padding(&pad)),
+                Item::Numeric(Numeric::Minute, pad) => format!("[minute{}]", padding(&pad)),
+                Item::Numeric(Numeric::Second, pad) => format!("[second{}]", padding(&pad)),
+                Item::Numeric(Numeric::Nanosecond, _) => "[subsecond digits:9]".to_string(),
+
+                Item::Fixed(Fixed::ShortMonthName) => "[month repr:short]".to_string(),
+                Item::Fixed(Fixed::LongMonthName) => "[month repr:long]".to_string(),
+                Item::Fixed(Fixed::ShortWeekdayName) => "[weekday repr:short]".to_string(),
+                Item::Fixed(Fixed::LongWeekdayName) => "[weekday]".to_string(),
+                Item::Fixed(Fixed::LowerAmPm) => "[period case:lower]".to_string(),
+                Item::Fixed(Fixed::UpperAmPm) => "[period]".to_string(),
+                Item::Fixed(Fixed::Nanosecond) => "[optional [.[subsecond]]]".to_string(),
+                Item::Fixed(Fixed::TimezoneOffset) =>
+                    "[offset_hour sign:mandatory][offset_minute]".to_string(),
+                Item::Fixed(Fixed::TimezoneOffsetColon) =>
+                    "[offset_hour sign:mandatory]:[offset_minute]".to_string(),
+
+                _ => return Err(Error::Msg(format!(
+                    "date format {} can't be used with the time crate", self.pattern
+                ))),
+            };
+
+            out.push_str(&component);
+        }
+
+        Ok(out)
+    }
+}
+
+/// Integer columns that look like they hold Unix timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Epoch
+{
+    Seconds,
+    Milliseconds,
+}
+
+impl Epoch
+{
+    /// An integer column is taken to be a timestamp if its name says it is a
+    /// time and all of its values fall between 2001 and 2100.
+    ///
+    /// The name is split into snake_case words, which are compared whole, so
+    /// `created_at`, `event_time` and `epoch` count but `candidate_id` and
+    /// `runtime_ms` don't.
+    pub(crate) fn detect(name: &str, min: i128, max: i128) -> Option<Self>
+    {
+        const SECONDS: (i128, i128) = (1_000_000_000, 4_102_444_800);
+        const WORDS: &[&str] = &["ts", "time", "timestamp", "epoch", "date", "datetime"];
+
+        let name = name.to_snake_case();
+        let words: Vec<&str> = name.split('_').collect();
+
+        let named =
+            matches!(words.last(), Some(&"at") | Some(&"ts"))
+            || words.iter().any(|w| WORDS.contains(w));
+
+        if !named
+        {
+            None
+        }
+        else if min >= SECONDS.0 && max <= SECONDS.1
+        {
+            Some(Epoch::Seconds)
+        }
+        else if min >= SECONDS.0 * 1000 && max <= SECONDS.1 * 1000
+        {
+            Some(Epoch::Milliseconds)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    /// The module to use with `#[serde(with = "...")]`.
+    pub(crate) fn module(self, backend: Backend, optional: bool) -> &'static str
+    {
+        match (backend, self, optional)
+        {
+            (Backend::Chrono, Epoch::Seconds, false) => "chrono::serde::ts_seconds",
+            (Backend::Chrono, Epoch::Seconds, true) => "chrono::serde::ts_seconds_option",
+            (Backend::Chrono, Epoch::Milliseconds, false) => "chrono::serde::ts_milliseconds",
+            (Backend::Chrono, Epoch::Milliseconds, true) => "chrono::serde::ts_milliseconds_option",
+            (Backend::Time, Epoch::Seconds, false) => "time::serde::timestamp",
+            (Backend::Time, Epoch::Seconds, true) => "time::serde::timestamp::option",
+            (Backend::Time, Epoch::Milliseconds, false) => "time::serde::timestamp::milliseconds",
+            (Backend::Time, Epoch::Milliseconds, true) => "time::serde::timestamp::milliseconds::option",
+        }
+    }
+}
+
+/// Picks a name for the format module of the `n`th format of `kind` used.
+pub(crate) fn module_name(kind: TemporalKind, n: usize) -> String
+{
+    match n
+    {
+        0 => format!("{}_format", kind.module_prefix()),
+        _ => format!("{}_format_{}", kind.module_prefix(), n + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn kind(pattern: &str) -> TemporalKind
+    {
+        Format::new(pattern).unwrap().kind
+    }
+
+    #[test]
+    fn kinds_of_the_default_formats()
+    {
+        use TemporalKind::*;
+
+        let kinds: Vec<TemporalKind> = DEFAULT_FORMATS.iter().map(|f| kind(f)).collect();
+
+        assert_eq!(kinds,
+        [
+            Date, Date, Date, Date, Date,
+            Time, Time,
+            DateTime, DateTime, DateTime, DateTime,
+            DateTimeTz, DateTimeTz, DateTimeTz, DateTimeTz,
+        ]);
+    }
+
+    #[test]
+    fn rejects_formats_that_are_not_dates_or_times()
+    {
+        assert!(Format::new("%%").is_err());
+        assert!(Format::new("%Y-%Q").is_err());
+    }
+
+    #[test]
+    fn zulu_and_offsets()
+    {
+        let zulu = Format::new("%Y-%m-%dT%H:%M:%S%.fZ").unwrap();
+        let offset = Format::new("%Y-%m-%dT%H:%M:%S%.f%:z").unwrap();
+
+        assert!(zulu.zulu);
+        assert!(!offset.zulu);
+
+        assert!(zulu.matches("2024-01-02T03:04:05Z"));
+        assert!(zulu.matches("2024-01-02T03:04:05.123Z"));
+        assert!(!zulu.matches("2024-01-02T03:04:05+01:00"));
+
+        assert!(offset.matches("2024-01-02T03:04:05+01:00"));
+        assert!(offset.matches("2024-01-02T03:04:05.5-08:00"));
+        assert!(!offset.matches("2024-01-02T03:04:05"));
+    }
+
+    #[test]
+    fn matches_dates_and_times()
+    {
+        assert!(Format::new("%d/%m/%Y").unwrap().matches("31/01/2024"));
+        assert!(!Format::new("%m/%d/%Y").unwrap().matches("31/01/2024"));
+        assert!(Format::new("%H:%M:%S%.f").unwrap().matches("23:59:59.25"));
+        assert!(!Format::new("%Y-%m-%d").unwrap().matches("2024-02-30"));
+    }
+
+    #[test]
+    fn epoch_names_are_whole_words()
+    {
+        let seconds = 1_700_000_000;
+
+        for name in ["created_at", "updatedAt", "event_time", "epoch", "ts", "login_ts", "Timestamp"].iter()
+        {
+            assert_eq!(Epoch::detect(name, seconds, seconds), Some(Epoch::Seconds), "{}", name);
+        }
+
+        for name in ["candidate_id", "runtime_ms", "status", "attempts", "cat", "tsunami"].iter()
+        {
+            assert_eq!(Epoch::detect(name, seconds, seconds), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn epoch_ranges()
+    {
+        let detect = |min, max| Epoch::detect("created_at", min, max);
+
+        assert_eq!(detect(1_000_000_000, 4_102_444_800), Some(Epoch::Seconds));
+        assert_eq!(detect(999_999_999, 1_700_000_000), None);
+        assert_eq!(detect(1_700_000_000, 4_102_444_801), None);
+
+        assert_eq!(detect(1_000_000_000_000, 4_102_444_800_000), Some(Epoch::Milliseconds));
+        assert_eq!(detect(1_700_000_000_000, 4_102_444_800_001), None);
+        assert_eq!(detect(1_700_000_000, 1_700_000_000_000), None);
+    }
+
+    #[test]
+    fn time_descriptions_escape_literals()
+    {
+        let format = Format::new("%Y-%m-%d [%H:%M] \\").unwrap();
+
+        assert_eq!(format.time_description(false).unwrap(), "[year]-[month]-[day] \\[[hour]:[minute]\\] \\\\");
+
+        assert_eq!(
+            format.time_description(true).unwrap(),
+            "[year]-[month padding:none]-[day padding:none] \\[[hour padding:none]:[minute padding:none]\\] \\\\"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! # `csv2struct-macros`
+//!
+//! `include_csv_struct!` generates a struct from a CSV file while your crate is
+//! compiled, so that it can't fall out of step with the file. It's re-exported
+//! from `csv2struct`, which is where to use it from.
+//!
+//! ```ignore
+//! csv2struct::include_csv_struct!("data/sample.csv", name = "Trade");
+//! ```
+//!
+//! The path is relative to the directory holding your `Cargo.toml`, and the
+//! struct is called `Record` unless `name` is given. The macro expands to the
+//! same code that `csv2struct` prints for the file with its default options, so
+//! it needs the same crates: `serde` with its `derive` feature, and `chrono` with
+//! its `serde` feature if there are any dates or times. The crate is rebuilt
+//! whenever the file changes.
+//!
+//! If the file can't be read, the compile error points at its path.
+
+use std::env;
+use std::fs::File;
+use std::path::PathBuf;
+
+use proc_macro::{Group, Literal, Span, TokenStream, TokenTree};
+
+/// An error message, and the part of the macro's input it's about.
+type Error = (Span, String);
+
+/// Generates a struct from a CSV file, along with the enums and helpers it
+/// needs.
+#[proc_macro]
+pub fn include_csv_struct(input: TokenStream) -> TokenStream
+{
+    match expand(input)
+    {
+        Ok(tokens) => tokens,
+        Err((span, msg)) => compile_error(span, &msg),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, Error>
+{
+    let args = Args::parse(input)?;
+    let options = csv2struct_core::Options::default();
+
+    let path =
+        PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap_or_default())
+        .join(&args.path);
+
+    // errors about the file point at its path
+    let fail = |msg: String| (args.path_span, format!("{}: {}", path.display(), msg));
+
+    let file = File::open(&path).map_err(|e| fail(e.to_string()))?;
+
+    let mut schema = csv2struct_core::infer(file, &options).map_err(|e| fail(e.to_string()))?;
+    schema.name = args.name.clone();
+
+    let code = csv2struct_core::render(&schema, &options).map_err(|e| fail(e.to_string()))?;
+    let mut tokens: TokenStream = code.parse().map_err(|e| fail(format!("{:?}", e)))?;
+
+    // refer to the file, so that the crate is rebuilt when it changes
+    let tracked = format!("const _: &[u8] = include_bytes!({:?});", path.to_string_lossy());
+    tokens.extend(tracked.parse::<TokenStream>().map_err(|e| fail(format!("{:?}", e)))?);
+
+    Ok(tokens)
+}
+
+/// The arguments to the macro: `"path"` or `"path", name = "Name"`.
+#[derive(Debug)]
+struct Args
+{
+    path: String,
+    path_span: Span,
+
+    // the name of the struct
+    name: String,
+}
+
+impl Args
+{
+    fn parse(input: TokenStream) -> Result<Self, Error>
+    {
+        let mut tokens = input.into_iter();
+
+        let (path, path_span) = match tokens.next()
+        {
+            Some(TokenTree::Literal(lit)) => (string(&lit)?, lit.span()),
+            other => return Err((span(other), "expected the path to a CSV file".to_string())),
+        };
+
+        let mut name = String::from("Record");
+
+        while let Some(token) = tokens.next()
+        {
+            match token
+            {
+                TokenTree::Punct(p) if p.as_char() == ',' => (),
+                other => return Err((other.span(), "expected `,`".to_string())),
+            }
+
+            // allow a trailing comma
+            let key = match tokens.next()
+            {
+                Some(TokenTree::Ident(key)) => key,
+                None => break,
+                other => return Err((span(other), "expected an option, like `name`".to_string())),
+            };
+
+            match tokens.next()
+            {
+                Some(TokenTree::Punct(p)) if p.as_char() == '=' => (),
+                other => return Err((span(other), "expected `=`".to_string())),
+            }
+
+            let value = match tokens.next()
+            {
+                Some(TokenTree::Literal(lit)) => (string(&lit)?, lit.span()),
+                other => return Err((span(other), "expected a string".to_string())),
+            };
+
+            match key.to_string().as_str()
+            {
+                "name" if is_ident(&value.0) => name = value.0,
+                "name" => return Err((value.1, format!("{:?} isn't a valid struct name", value.0))),
+                other => return Err((key.span(), format!("unknown option: {}", other))),
+            }
+        }
+
+        Ok(Self { path, path_span, name })
+    }
+}
+
+/// The text of a string literal, which may be raw.
+fn string(lit: &Literal) -> Result<String, Error>
+{
+    let text = lit.to_string();
+    let error = || (lit.span(), "expected a string".to_string());
+
+    if let Some(raw) = text.strip_prefix('r')
+    {
+        let raw = raw.trim_matches('#');
+
+        return
+            raw.strip_prefix('"')
+            .and_then(|r| r.strip_suffix('"'))
+            .map(|r| r.to_string())
+            .ok_or_else(error);
+    }
+
+    let inner =
+        text.strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .ok_or_else(error)?;
+
+    let mut out = String::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next()
+    {
+        if c != '\\'
+        {
+            out.push(c);
+            continue;
+        }
+
+        // paths and names only need these
+        match chars.next()
+        {
+            Some(c @ '\\') | Some(c @ '"') | Some(c @ '\'') => out.push(c),
+            _ => return Err((lit.span(), "unsupported escape in string".to_string())),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Whether `name` can be used as the name of a struct.
+fn is_ident(name: &str) -> bool
+{
+    !name.is_empty()
+    && !name.starts_with(|c: char| c.is_ascii_digit())
+    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Where a token is, or the macro call itself if there wasn't one.
+fn span(token: Option<TokenTree>) -> Span
+{
+    token.map_or_else(Span::call_site, |t| t.span())
+}
+
+/// A `compile_error!` with `msg`, pointing at `span`.
+fn compile_error(span: Span, msg: &str) -> TokenStream
+{
+    format!("compile_error!({:?});", msg)
+    .parse::<TokenStream>()
+    .expect("compile_error! is valid")
+    .into_iter()
+    .map(|token| respan(token, span))
+    .collect()
+}
+
+/// Moves a token, and everything inside it, to `span`.
+fn respan(token: TokenTree, span: Span) -> TokenTree
+{
+    let mut token = match token
+    {
+        TokenTree::Group(group) =>
+        {
+            let inner = group.stream().into_iter().map(|t| respan(t, span)).collect();
+            TokenTree::Group(Group::new(group.delimiter(), inner))
+        }
+
+        other => other,
+    };
+
+    token.set_span(span);
+    token
+}
@@ -0,0 +1,5 @@
+#[test]
+fn compile_fail()
+{
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,40 @@
+//! The macro, expanded several times in one module and used to read the file
+//! it was expanded from.
+
+use chrono::NaiveDate;
+
+csv2struct_macros::include_csv_struct!("tests/fixtures/trades.csv");
+csv2struct_macros::include_csv_struct!("tests/fixtures/trades.csv", name = "Trade");
+csv2struct_macros::include_csv_struct!("tests/fixtures/trades.csv", name = "Order",);
+
+fn read<T: serde::de::DeserializeOwned>() -> Vec<T>
+{
+    csv::Reader::from_path(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/trades.csv"))
+    .unwrap()
+    .deserialize()
+    .collect::<Result<_, _>>()
+    .unwrap()
+}
+
+#[test]
+fn reads_the_file_it_was_made_from()
+{
+    let records: Vec<Record> = read();
+
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0].id, 1u8);
+    assert_eq!(records[0].state, State::Open);
+    assert_eq!(records[1].price, None);
+    assert_eq!(records[3].price, Some(2.5f32));
+    assert_eq!(records[3].opened, NaiveDate::from_ymd_opt(2024, 1, 5).unwrap());
+}
+
+#[test]
+fn names_the_struct()
+{
+    let trades: Vec<Trade> = read();
+    let orders: Vec<Order> = read();
+
+    assert_eq!(trades[1].state, TradeState::Shut);
+    assert_eq!(orders[1].state, OrderState::Shut);
+}
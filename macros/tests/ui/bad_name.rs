@@ -0,0 +1,3 @@
+csv2struct_macros::include_csv_struct!("tests/fixtures/trades.csv", name = "not a name");
+
+fn main() {}
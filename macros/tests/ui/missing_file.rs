@@ -0,0 +1,3 @@
+csv2struct_macros::include_csv_struct!("tests/fixtures/missing.csv", name = "Trade");
+
+fn main() {}
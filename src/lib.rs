@@ -0,0 +1,280 @@
+//! # `csv2struct`
+//!
+//! Generates struct definitions from CSV using some very basic rules.
+//!
+//! # Example
+//!
+//! ```bash
+//! $ cat test.csv
+//! foo,bar,baz,qux
+//! 1,2,3,green
+//! 4.4,5,6,red
+//! 7.2,,8,blue
+//! 3,9,10,red
+//! 5.5,11,12,green
+//! 0.1,,14,red
+//!
+//! $ cat test.csv | csv2struct
+//! #[derive(
+//!     Debug,
+//!     Clone,
+//!     Copy,
+//!     PartialEq,
+//!     PartialOrd,
+//!     serde::Deserialize,
+//!     serde::Serialize
+//! )]
+//! pub struct Record {
+//!     pub foo: f32,
+//!     pub bar: Option<u8>,
+//!     pub baz: u8,
+//!     pub qux: Qux,
+//! }
+//!
+//! #[derive(
+//!     Debug,
+//!     Clone,
+//!     Copy,
+//!     PartialEq,
+//!     Eq,
+//!     PartialOrd,
+//!     Ord,
+//!     Hash,
+//!     serde::Deserialize,
+//!     serde::Serialize
+//! )]
+//! pub enum Qux {
+//!     #[serde(rename = "green")]
+//!     Green,
+//!     #[serde(rename = "red")]
+//!     Red,
+//!     #[serde(rename = "blue")]
+//!     Blue,
+//! }
+//! ```
+//!
+//! The CSV can also be given as arguments, which may be files, globs or
+//! directories (which are searched for `.csv` and `.tsv` files), with `-` meaning
+//! stdin:
+//!
+//! ```bash
+//! $ csv2struct data/*.csv
+//! ```
+//!
+//! All of the inputs must have the same columns, unless `--merge` is given. In that
+//! case columns are matched up by name, and the struct has every column from every
+//! input. Columns that only some of the inputs have are reported, and become
+//! `Option`s (with `#[serde(default)]` where needed), so the struct can read any of
+//! the inputs.
+//!
+//! Alternatively, `--per-file` generates a struct for each input, named after the
+//! file, so `orders.csv` and `customers.csv` give `Order` and `Customer`. Factor
+//! columns with exactly the same levels in different files share an enum, which
+//! is named after the struct it was first made for, as in `OrderStatus`.
+//!
+//! The code is printed to stdout, unless `-o`/`--output` names a file to write it
+//! to. The file is only written if the code has changed, so that anything watching
+//! it (like Cargo) isn't woken needlessly, and it's replaced all at once, so it's
+//! never seen half-written. With `--check`, nothing is written, but csv2struct
+//! fails if the file isn't what would be written to it; this makes sure that
+//! checked-in code is up to date.
+//!
+//! Each input's delimiter is worked out from its first few lines, choosing
+//! whichever of `,`, tab, `;` and `|` splits them most consistently, unless it's
+//! given with `--delimiter`. The other settings of the CSV reader can be changed
+//! with `--quote`, `--escape`, `--no-double-quote`, `--comment`, `--terminator`
+//! and `--trim`. The generated struct doesn't know about any of this, so the
+//! reader you use it with needs the same settings.
+//!
+//! If the CSV has no header row, pass `--no-header`; the fields are then called
+//! `column_1`, `column_2` and so on, unless names are given with `--names a,b,c`
+//! or `--names-file`. The struct then has to be read by position, with
+//! `csv::ReaderBuilder::new().has_headers(false)`. As a check, there's a warning
+//! when the first row looks like data (all numbers or dates) but is being used as
+//! a header, or looks like a header (all text, above typed columns) but is being
+//! used as data.
+//!
+//! There are two sets of rules at play. First, we apply the following set of
+//! rules to each value in each column and record the results. 
+//!
+//! ```text
+//! if value == ""                       => Empty
+//! if value is a null value             => Null
+//! if value is a true or false value    => Boolean(value)
+//! if let Ok(i) = value.parse::<i128>() => Integer(i)
+//! if let Ok(r) = value.parse::<f64>()  => Real(r)
+//! if value matches a date format       => Temporal(value)
+//! else                                 => Factor(value)
+//! ```
+//!
+//! Numbers are only what `str::parse` understands, unless `--number-format` is
+//! given. With `dot` (`1,234.50`), `comma` (`1.234,50`) or `auto` (either, going by
+//! the separators in each value), values like `$1,234.50`, `1.234,50 €`, `12 %` and
+//! `(45.00)` are Integer or Real once their formatting is stripped off: currency
+//! symbols, percent signs, thousands separators, and parentheses, which mean the
+//! number is negative. Percentages keep their value, so `12 %` is `12`. Columns
+//! with any such values get a `#[serde(deserialize_with = ...)]` helper that
+//! strips the formatting the same way.
+//!
+//! The results aren't kept: each column just keeps a running summary of them
+//! (counts, the integer range, the date formats all of the values match, and the
+//! first few distinct factor levels), so inputs of any size are read in bounded
+//! memory.
+//!
+//! Next, we apply the following rules to the results for each column:
+//!
+//! - If all of the values were parsed as Boolean; then treat the column as `bool`.
+//!   The true and false values default to the usual spellings (`true`, `yes`, `Y`,
+//!   `t`, `1`, ...) and can be changed with `--true-values` and `--false-values`.
+//!   If the column uses anything but `true` and `false`, a
+//!   `#[serde(deserialize_with = ...)]` helper that understands them is generated
+//!   too. Otherwise, Boolean values are re-read as one of the kinds below; so a
+//!   column of `0`, `1` and `2` is still an integer.
+//! - If all of the values were parsed as Temporal, and there is a format that all
+//!   of them match; then treat the column as a date (`chrono::NaiveDate`), time
+//!   (`chrono::NaiveTime`), timestamp (`chrono::NaiveDateTime`) or timestamp with
+//!   an offset (`chrono::DateTime<chrono::Utc>`) as appropriate. A module for use
+//!   with `#[serde(with = ...)]` that reads and writes that format is generated
+//!   alongside the struct. ISO-8601 and a few other common formats are looked for
+//!   by default; add your own with `--date-format`, and pass `--time-crate` to use
+//!   the `time` crate's types instead. Otherwise, Temporal values are treated as
+//!   factor levels.
+//! - If any of the values were parsed as Factor; then treat the column as a factor.
+//!   That is, unless it has more than 32 distinct levels, or more distinct levels
+//!   than half the number of rows; in which case it's a `String`. These limits can
+//!   be changed with `--max-levels` and `--max-level-ratio`. Each distinct level
+//!   becomes one enum variant, in the order they were first seen; pass
+//!   `--level-order alphabetical` or `--level-order frequency` (most common first)
+//!   to change this.
+//! - Otherwise, if not all of the values were parsed as Integer; then treat the column as real.
+//!   This is `f32` if every value survives a round trip through `f32` unchanged,
+//!   and `f64` if any of them has too many digits or is too large. Pass
+//!   `--always-f64` to use `f64` for every real column. With `--decimal`, columns
+//!   that look like money use `rust_decimal::Decimal` instead: those whose names
+//!   say so (`price`, `amount`, `total`, ...) and those where every value has the
+//!   same number of decimal places. The field's doc comment says how many decimal
+//!   places its values have. The generated code needs `rust_decimal` with the
+//!   `serde-with-str` feature.
+//! - Otherwise, treat the column as integer, using the narrowest type that holds
+//!   every value: `u8`/`u16`/`u32`/`u64` if none of them are negative, otherwise
+//!   `i8`/`i16`/`i32`/`i64`/`i128`. Pass `--always-i64` to use `i64` for every
+//!   integer column whose values fit in it.
+//!   Integer columns whose names look like times (`created_at`, `timestamp`, ...)
+//!   and whose values are all Unix timestamps between 2001 and 2100, in seconds
+//!   or milliseconds, become `chrono::DateTime<chrono::Utc>` instead. The words of
+//!   the name are compared whole, so `candidate_id` doesn't count as a date. Pass
+//!   `--no-epochs` to keep such columns as integers.
+//! - If any of the values were missing, then apply the above rules to the values
+//!   that were present, and wrap the result in `Option`. Besides empty values,
+//!   these are the null values `NA`, `N/A`, `NULL`, `null`, `\N`, `-` and `NaN`,
+//!   which can be changed with `--null-values`. If a column has any of those, its
+//!   field gets a `#[serde(deserialize_with = ...)]` helper that reads them as
+//!   `None`.
+//!
+//! Finally, we generate a struct definition with one field for each column. For
+//! factors, we generate an enum as well. The code is built as Rust tokens rather
+//! than text, formatted much as `rustfmt` would, and checked to parse before it's
+//! printed.
+//!
+//! Field names are the column headers converted to snake_case, with anything that
+//! can't appear in an identifier dropped (`First Name` becomes `first_name`, and
+//! `%change` becomes `percent_change`). Names starting with a digit get a `field_`
+//! prefix, keywords are written as raw identifiers (`r#type`), and repeated names
+//! are numbered (`a`, `a_2`). Whenever the name had to change, the field gets a
+//! `#[serde(rename = "...")]` with the original header.
+//!
+//! Enum variants are made from factor levels the same way, but in PascalCase:
+//! `in-progress` becomes `InProgress`, `N/A` becomes `NA` and `3rd` becomes
+//! `Level3Rd`. Levels that end up with the same name are numbered (`Red` and `red`
+//! become `Red` and `Red2`), and again any variant whose name differs from its
+//! level gets a `#[serde(rename = "...")]` so it reads and writes the exact text.
+//!
+//! An enum can only read the levels that were in the input. If other values might
+//! turn up later, pass `--open-enums` to give every enum an `Other(String)`
+//! variant that holds anything else. Such enums can't be `Copy`, and get
+//! hand-written `as_str`, `FromStr`, `Deserialize` and `Serialize` implementations
+//! instead of derived ones.
+//!
+//! Every generated type derives `Debug`, `Clone`, `PartialEq` and `PartialOrd`.
+//! `Copy` is added unless a field is a `String`, and `Eq`, `Ord` and `Hash` unless
+//! a field is a float. Pass `--derive` with a comma-separated list of traits to
+//! derive anything else as well.
+//!
+//! The types also derive `serde::Deserialize` and `serde::Serialize`, so that
+//! `csv::Reader::deserialize` can read the original file straight into `Record`s
+//! (and `csv::Writer::serialize` can write them back out). This needs `serde` with
+//! its `derive` feature, and `chrono` with its `serde` feature if there are any
+//! dates or times. Pass `--no-serde` to leave out the serde derives along with all
+//! of the attributes and helpers that go with them.
+//!
+//! The helpers that the attributes refer to go in a private module named after the
+//! struct, like `trade_serde`, and enums are named after their struct as well as
+//! their column, like `TradeState`, unless the struct is just `Record`. So the
+//! code generated for differently named structs can sit side by side in one
+//! module, even when it's generated separately.
+//!
+//! # Library
+//!
+//! Everything the command line tool does is also available as a library. `infer`
+//! reads some CSV and returns a `Schema`: the name of the struct, and the name and
+//! kind of each column, which can be looked at or changed before `render` turns it
+//! into code. `Options` holds the same settings as the command line options.
+//!
+//! ```rust
+//! let options = csv2struct::Options::default();
+//! let schema = csv2struct::infer("id,colour\n1,red\n2,blue\n".as_bytes(), &options).unwrap();
+//!
+//! assert_eq!(schema.columns[1].name, "colour");
+//! print!("{}", csv2struct::render(&schema, &options).unwrap());
+//! ```
+//!
+//! To read several inputs into one schema, use an `Inference`, and `render_all` to
+//! generate code for several schemas at once.
+//!
+//! # Compile time
+//!
+//! `include_csv_struct!` generates the struct while your crate is compiled, so
+//! that it stays in step with a CSV file that's checked in alongside the code:
+//!
+//! ```rust,ignore
+//! csv2struct::include_csv_struct!("data/sample.csv", name = "Trade");
+//! ```
+//!
+//! The path is relative to the directory holding your `Cargo.toml`, and the struct
+//! is called `Record` unless `name` is given. The code is the same as `csv2struct`
+//! prints for the file with its default options, and your crate is rebuilt
+//! whenever the file changes. If the file can't be read, the compile error points
+//! at its path.
+//!
+//! Procedural macros have to live in a crate of their own, so the macro is defined
+//! in `csv2struct-macros`, in `macros/`, and re-exported from here. The inference
+//! and code generation that both crates need are in `csv2struct-core`, in `core/`.
+//!
+//! # Build scripts
+//!
+//! `csv2struct::Builder` generates code from `build.rs` instead, writing it into
+//! `OUT_DIR` for your crate to `include!`:
+//!
+//! ```rust,ignore
+//! // build.rs
+//! fn main()
+//! {
+//!     csv2struct::Builder::new()
+//!     .input("data/trades.csv")
+//!     .name("Trade")
+//!     .generate("trade.rs")
+//!     .unwrap();
+//! }
+//!
+//! // src/lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/trade.rs"));
+//! ```
+//!
+//! Add as many inputs as you like; `merge` and `per_file` work like `--merge` and
+//! `--per-file`, and `options` takes an `Options` for everything else. Cargo is
+//! told to run the build script again whenever one of the inputs changes, and any
+//! warnings about them are passed on to Cargo. As with `--output`, the file is
+//! only written if the code has changed.
+
+pub use csv2struct_core::*;
+pub use csv2struct_macros::include_csv_struct;
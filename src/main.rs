@@ -1,314 +1,314 @@
-//! # `csv2struct`
-//!
-//! Generates struct definitions from CSV using some very basic rules.
-//!
-//! # Example
-//!
-//! ```bash
-//! $ cat test.csv
-//! foo,bar,baz,qux
-//! 1,2,3,green
-//! 4.4,5,6,red
-//! 7.2,,8,blue
-//!
-//! $ cat test.csv | csv2struct
-//! #[derive(Debug, Clone, Copy, Eq)]
-//! pub struct Record {
-//!     pub foo: f32,
-//!     pub bar: Option<i32>,
-//!     pub baz: i32,
-//!     pub qux: Qux,
-//! }
-//! 
-//! #[derive(Debug, Clone, Copy, Eq)]
-//! pub enum Qux {
-//!     Green,
-//!     Red,
-//!     Blue,
-//! }
-//! ```
-//!
-//! There are two sets of rules at play. First, we apply the following set of
-//! rules to each value in each column and record the results. 
-//!
-//! ```
-//! if value == ""                        => Empty
-//! if let Some(_) = value.parse::<i32>() => Integer
-//! if let Some(_) = value.parse::<f32>() => Real
-//! else                                  => Factor(value)
-//! ```
-//!
-//! Next, we apply the following rules to the results for each column:
-//!
-//! - If any of the values were parsed as Factor; then treat the column as a factor.
-//! - Otherwise, if not all of the values were parsed as Integer; then treat the column as real.
-//! - Otherwise, treat the column as integer.
-//! - If any of the values were missing, then apply the above rules to the values
-//!   that were present, and wrap the result in `Option`.
-//!
-//! Finally, we generate a struct definition with one field for each column. For
-//! factors, we generate an enum as well.
-
-use std::io;
-use std::collections::HashMap;
-
-use csv;
-
-use inflector::Inflector;
-
-/// Result alias.
-pub type Result<T> = std::result::Result<T, Error>;
-
-#[derive(Debug)]
-pub enum Error
-{
-    Msg(String),
-}
+//! The `csv2struct` command line tool. See the library's documentation, or
+//! `csv2struct --help`, for what it does.
 
-impl<'a, T: ToString> From<T> for Error
-{
-    fn from(t: T) -> Self
-    {
-        Error::Msg(t.to_string())
-    }
-}
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
 
-/// Represents an attempt at type inference.
-#[derive(Debug)]
-struct RecordType
-{
-    // the fields in the record
-    fields: Vec<Field>
-}
+use csv2struct::{dialect, input, Backend, Error, Format, LevelOrder, NumberFormat, Options, Result, Schema};
 
-impl RecordType
-{
-    fn with(fields: Vec<Field>) -> Self
-    {
-        Self
-        {
-            fields
-        }
-    }
+const USAGE: &str = "\
+Usage: csv2struct [OPTIONS] [INPUT]...
 
-}
+Reads CSV from each INPUT, which may be a file, a glob such as `data/*.csv`, a
+directory to search for .csv and .tsv files, or `-` for stdin. With no INPUT,
+reads stdin. Every input must have the same columns, unless --merge or
+--per-file is given.
 
-/// A field in a record.
-#[derive(Debug, Clone)]
-struct Field
-{
-    // the name of the field
-    name: String,
+Options:
+    --merge         Allow inputs with different columns, generating one struct
+                    for all of them; columns that some inputs don't have become
+                    optional
 
-    // the type of the field
-    kind: FieldKind,
-}
+    --per-file      Generate a struct for each input, named after the file, with
+                    enums shared between them where their levels are the same
 
-impl Field
-{
-    fn with(name: String, kind: FieldKind) -> Self
-    {
-        Self
-        {
-            name,
-            kind,
-        }
-    }
-}
+    -o, --output <FILE>
+                    Write the code to this file instead of stdout; the file is
+                    replaced all at once, and only if the code has changed
 
+    --check         Don't write anything, but fail if the file given with
+                    --output isn't what would be written to it
 
-/// Represents the different kinds of fields that a record can have
-#[derive(Debug, Clone)]
-enum FieldKind
-{
-    Integer,
-    Real,
-    Factor(String),
-    Empty,
-}
+    --delimiter <CHAR>
+                    The field delimiter, e.g. `;` or `tab`; by default it is
+                    worked out from the first few lines of each input
 
-impl FieldKind
-{
-    fn parse(f: &str) -> Self
-    {
-        if f == ""
-        {
-            FieldKind::Empty
-        }
+    --quote <CHAR>  The quote character [default: \"]
 
-        else
-        {
-                f.parse::<i32>().map(|_i| FieldKind::Integer)
-            .or(f.parse::<f32>().map(|_i| FieldKind::Real))
-            .unwrap_or(FieldKind::Factor(f.to_string()))
-        }
-    }
-}
+    --escape <CHAR> An escape character for quotes inside quoted fields, such
+                    as `\\`; usually given along with --no-double-quote
+
+    --no-double-quote
+                    Don't treat two quotes in a quoted field as one quote
+
+    --comment <CHAR>
+                    Skip lines that start with this character
+
+    --terminator <CHAR>
+                    The record terminator [default: any of \\r, \\n or \\r\\n]
+
+    --trim <WHAT>   Trim whitespace from `headers`, `fields` or `all`
+
+    --no-header     The first row is data, not a header; columns are called
+                    `column_1`, `column_2`, ... unless --names is given
+
+    --names <A,B,...>
+                    Comma-separated names for the columns; implies --no-header
+
+    --names-file <FILE>
+                    Like --names, but reads the names from a file, one per line
+                    or separated by commas
+
+    --always-i64    Use i64 for every integer column whose values fit in it
+    --always-f64    Use f64 for every real column
+
+    --no-epochs     Keep integer columns that look like Unix timestamps as
+                    integers, rather than making them dates and times
+
+    --number-format <FORMAT>
+                    How numbers are written: `plain`, `dot` (1,234.50),
+                    `comma` (1.234,50) or `auto` (either, going by the
+                    separators in each value); apart from `plain`, currency
+                    symbols, percent signs and accounting-style negatives like
+                    `(45.00)` are allowed too [default: plain]
+
+    --decimal       Use `rust_decimal::Decimal` rather than a float for real
+                    columns that look like money: those with money-like names
+                    (`price`, `amount`, ...), and those where every value has
+                    the same number of decimal places
+
+    --null-values <A,B,...>
+                    Comma-separated values that mean a value is missing, as
+                    well as an empty one [default: NA,N/A,NULL,null,\\N,-,NaN]
+
+    --true-values <A,B,...>
+                    Comma-separated values that mean `true`
+                    [default: true,True,TRUE,yes,Yes,YES,y,Y,t,T,1]
+
+    --false-values <A,B,...>
+                    Comma-separated values that mean `false`
+                    [default: false,False,FALSE,no,No,NO,n,N,f,F,0]
+
+    --date-format <FORMAT>
+                    A strftime format to look for dates and times in, before
+                    the built-in ones; may be given more than once
+
+    --time-crate    Use `time` types for dates and times instead of `chrono`
+
+    --max-levels <N>
+                    Use `String` instead of an enum for columns with more than
+                    this many distinct values [default: 32]
+
+    --max-level-ratio <R>
+                    Use `String` instead of an enum for columns where the
+                    number of distinct values is more than this fraction of the
+                    number of rows [default: 0.5]
 
-/// Keeps track of, for each field, what types we have seen for it.
-/// For example, processing
-///
-/// ```
-/// a,b,c
-/// 1,2,red
-/// 4.4,5,green
-/// ```
-///
-/// would yield an index
-///
-/// ```
-/// [
-///     (a, [Integer,       Real           ]),
-///     (b, [Integer,       Integer        ]),
-///     (c, [Factor("red"), Factor("green")]),
-/// ]
-/// ```
-///
-/// This is then used to generate the type definitions.
-#[derive(Debug)]
-struct Index
+    --derive <A,B,...>
+                    Extra traits to derive for the generated types, on top of
+                    the standard ones that the field types allow
+
+    --no-serde      Don't derive `serde::Deserialize` and `serde::Serialize`,
+                    or generate any of the serde glue for the fields
+
+    --level-order <ORDER>
+                    The order of enum variants: `first-seen`, `alphabetical`
+                    or `frequency` (most common first) [default: first-seen]
+
+    --open-enums    Give enums an `Other(String)` variant for values that
+                    weren't in the input, instead of failing to read them
+
+    -h, --help      Print this message
+";
+
+/// Command line arguments.
+#[derive(Debug, Default)]
+struct Args
 {
-    inner: Vec<(String, Vec<FieldKind>)>,
+    // everything that the library is told
+    options: Options,
+
+    // the files, globs and directories to read, as given
+    inputs: Vec<String>,
+
+    // whether inputs may have different columns
+    merge: bool,
+
+    // whether to generate a struct for each input, rather than one for all
+    per_file: bool,
+
+    // the file to write the code to, rather than stdout
+    output: Option<PathBuf>,
+
+    // whether to check the output file is up to date, rather than write it
+    check: bool,
 }
 
-impl Index
+impl Args
 {
-    fn new() -> Self
+    fn parse() -> Result<Self>
     {
-        Self { inner: Vec::new() }
-    }
+        let mut parsed = Self::default();
+        let opts = &mut parsed.options;
+        let mut args = env::args().skip(1);
+        let mut date_formats = Vec::new();
 
-    fn add(&mut self, rt: RecordType)
-    {
-        // add self fields to index
-        for field in rt.fields.iter()
+        while let Some(arg) = args.next()
         {
-            match self.inner.iter_mut().find(|i| i.0 == field.name)
+            // the value for options that take one
+            let mut value = || args.next().ok_or_else(||
+                Error::Msg(format!("missing value for {}", arg))
+            );
+
+            match arg.as_str()
             {
-                Some((_s, ref mut v)) =>
-                    v.push(field.kind.clone()),
+                "--merge" => parsed.merge = true,
+                "--per-file" => parsed.per_file = true,
 
-                None =>
-                    self.inner.push(
-                        (field.name.clone(), vec![field.kind.clone()])
-                    ),
-            }
-        }
-    }
+                "-o" | "--output" => parsed.output = Some(PathBuf::from(value()?)),
+                "--check" => parsed.check = true,
 
-    fn to_struct_defs(self)
-    {
-        println!("#[derive(Debug, Clone, Copy, Eq)]");
-        print!("pub struct Record {{\n");
+                "--delimiter" => opts.dialect.delimiter = Some(dialect::byte(&value()?)?),
+                "--quote" => opts.dialect.quote = dialect::byte(&value()?)?,
+                "--escape" => opts.dialect.escape = Some(dialect::byte(&value()?)?),
+                "--no-double-quote" => opts.dialect.double_quote = false,
+                "--comment" => opts.dialect.comment = Some(dialect::byte(&value()?)?),
+                "--terminator" => opts.dialect.terminator = csv::Terminator::Any(dialect::byte(&value()?)?),
 
-        let mut factor_defs = Vec::new();
+                "--trim" => opts.dialect.trim = match value()?.as_str()
+                {
+                    "headers" => csv::Trim::Headers,
+                    "fields" => csv::Trim::Fields,
+                    "all" => csv::Trim::All,
+                    other => return Err(Error::Msg(format!("unknown trim: {}", other))),
+                },
 
-        for (name, kinds) in self.inner.into_iter()
-        {
-            print!("    pub {}: ", name);
+                "--no-header" => opts.dialect.header = false,
 
-            // if any are factor -> factor
-            // else if not all are integer -> real
-            // else -> integer
-            // 
-            // if any are empty, then it's Option of the above
+                "--names" =>
+                {
+                    opts.names = split_list(&value()?);
+                    opts.dialect.header = false;
+                }
 
-            let test_empty =
-                kinds.iter()
-                .any(|k| match k { FieldKind::Empty => true, _ => false });
+                "--names-file" =>
+                {
+                    let path = value()?;
 
-            let test_factor =
-                kinds.iter()
-                .filter_map(|k| match k { FieldKind::Empty => None, _ => Some(k) })
-                .any(|k| match k { FieldKind::Factor(_) => true, _ => false });
+                    opts.names =
+                        fs::read_to_string(&path)
+                        .map_err(|e| Error::Msg(format!("{}: {}", path, e)))?
+                        .split([',', '\n'])
+                        .map(|n| n.trim().to_string())
+                        .filter(|n| !n.is_empty())
+                        .collect();
 
-            let test_real =
-                kinds.iter()
-                .filter_map(|k| match k { FieldKind::Empty => None, _ => Some(k) })
-                .any(|k| match k { FieldKind::Integer => false, _ => true });
+                    opts.dialect.header = false;
+                }
 
-            let mut type_name = String::new();
+                "--always-i64" => opts.always_i64 = true,
+                "--always-f64" => opts.always_f64 = true,
+                "--no-epochs" => opts.epochs = false,
 
-            if test_factor
-            {
-                type_name = name.to_pascal_case();
-                let mut factor_def = "#[derive(Debug, Clone, Copy, Eq)]\n".to_string();
-                factor_def.extend(format!("pub enum {} {{\n", type_name).chars());
+                "--number-format" => opts.number_format = NumberFormat::from_name(&value()?)?,
 
-                kinds.into_iter()
-                .filter_map(|k| match k { FieldKind::Factor(s) => Some(s), _ => None })
-                .for_each(|level|
-                    factor_def.extend(
-                        format!("    {},\n", level.to_pascal_case()).chars()
-                    )
-                );
+                "--decimal" => opts.decimal = true,
 
-                factor_def.extend("}\n\n".chars());
+                "--null-values" => opts.null_values = split_list(&value()?),
 
-                factor_defs.push(factor_def);
-            }
+                "--true-values" => opts.true_values = split_list(&value()?),
+                "--false-values" => opts.false_values = split_list(&value()?),
 
-            else if test_real
-            {
-                type_name = String::from("f32");
-            }
+                "--date-format" => date_formats.push(Format::new(&value()?)?),
+                "--time-crate" => opts.backend = Backend::Time,
 
-            else
-            {
-                type_name = String::from("i32");
-            }
+                "--max-levels" => opts.max_levels = value()?.parse()?,
+                "--max-level-ratio" => opts.max_level_ratio = value()?.parse()?,
 
-            if test_empty
-            {
-                type_name = format!("Option<{}>", type_name);
-            }
+                "--derive" => opts.derives.extend(split_list(&value()?)),
+                "--no-serde" => opts.serde = false,
+
+                "--open-enums" => opts.open_enums = true,
+
+                "--level-order" => opts.level_order = match value()?.as_str()
+                {
+                    "first-seen" => LevelOrder::FirstSeen,
+                    "alphabetical" => LevelOrder::Alphabetical,
+                    "frequency" => LevelOrder::Frequency,
+                    other => return Err(Error::Msg(format!("unknown level order: {}", other))),
+                },
 
-            print!("{},\n", type_name);
+                "-h" | "--help" =>
+                {
+                    print!("{}", USAGE);
+                    process::exit(0);
+                }
+
+                _ if arg == "-" || !arg.starts_with('-') => parsed.inputs.push(arg),
+
+                _ => return Err(Error::Msg(format!("unrecognised argument: {}", arg))),
+            }
         }
 
-        print!("}}\n\n");
+        opts.date_formats.splice(0..0, date_formats);
 
-        for factor_def in factor_defs.into_iter()
+        if parsed.check && parsed.output.is_none()
         {
-            print!("{}", factor_def);
+            return Err(Error::Msg("--check needs the file to check, given with --output".to_string()));
         }
+
+        Ok(parsed)
     }
 }
 
-fn main() -> Result<()> {
-    let mut rdr = csv::Reader::from_reader(io::stdin());
+/// Splits a comma-separated command line value.
+fn split_list(value: &str) -> Vec<String>
+{
+    value.split(',').map(|v| v.to_string()).collect()
+}
 
-    let headers: Vec<String> =
-        rdr.headers().unwrap().iter()
-        .map(|h| h.to_string())
-        .collect();
+fn main()
+{
+    if let Err(e) = run()
+    {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let args = Args::parse()?;
+    let opts = &args.options;
 
-    // this is responsible for, for each field, keeping track of what type it
-    // looks like
-    let mut index = Index::new();
+    let inputs = input::expand(&args.inputs)?;
+    let schemas = csv2struct::infer_inputs(&inputs, None, args.merge, args.per_file, opts)?;
 
-    // for each record, see what the RecordType looks like
-    for result in rdr.deserialize()
+    for warning in schemas.iter().flat_map(|s: &Schema| s.warnings.iter())
     {
-        let record: HashMap<String, String> = result?;
+        eprintln!("warning: {}", warning);
+    }
 
-        let mut fields = Vec::new();
+    let code = csv2struct::render_all(&schemas, opts)?;
 
-        for header in &headers
+    match args.output
+    {
+        Some(path) if args.check =>
         {
-            fields.push(
-                Field::with(
-                    header.to_string(),
-                    FieldKind::parse(record.get(header).unwrap())
-                )
-            );
+            if fs::read(&path).ok().as_deref() != Some(code.as_bytes())
+            {
+                return Err(Error::Msg(format!("{} is out of date", path.display())));
+            }
         }
 
-        index.add(RecordType::with(fields));
-    }
+        Some(path) =>
+        {
+            csv2struct::write_if_changed(&path, &code)?;
+        }
 
-    index.to_struct_defs();
+        None => print!("{}", code),
+    }
 
     Ok(())
 }
-